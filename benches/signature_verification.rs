@@ -0,0 +1,67 @@
+use blockchain::crypto::{verify_batch, SignatureCheck};
+use blockchain::{Block, Transaction, Wallet};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+fn signed_block(size: usize) -> Block {
+    let wallets: Vec<Wallet> = (0..16).map(|i| Wallet::new(format!("sender {}", i))).collect();
+    let recipient = Wallet::new("recipient".to_string()).address();
+
+    let transactions = (0..size)
+        .map(|i| {
+            let wallet = &wallets[i % wallets.len()];
+            let mut tx = Transaction::new(wallet.address(), recipient.clone(), 1.0, None).unwrap();
+            tx.nonce = (i / wallets.len()) as u64;
+            tx.sign(wallet).unwrap();
+            tx
+        })
+        .collect();
+
+    Block::new(1, transactions, "0".repeat(64), "0".repeat(64))
+}
+
+fn bench_signature_verification(c: &mut Criterion) {
+    let mut group = c.benchmark_group("signature_verification");
+    group.sample_size(20);
+
+    for size in [1_000, 4_000] {
+        let block = signed_block(size);
+        let messages: Vec<Vec<u8>> = block
+            .transactions
+            .iter()
+            .map(|tx| tx.signable_data().unwrap())
+            .collect();
+        let checks: Vec<SignatureCheck> = block
+            .transactions
+            .iter()
+            .zip(&messages)
+            .map(|(tx, message)| SignatureCheck {
+                message,
+                signature: tx.signature.as_ref().unwrap(),
+                public_key: tx.from_public_key.as_ref().unwrap(),
+            })
+            .collect();
+
+        group.throughput(Throughput::Elements(size as u64));
+
+        group.bench_with_input(BenchmarkId::new("individual", size), &checks, |b, checks| {
+            b.iter(|| {
+                assert!(checks
+                    .iter()
+                    .all(|check| check.public_key.verify(check.message, check.signature)))
+            })
+        });
+
+        group.bench_with_input(BenchmarkId::new("batch", size), &checks, |b, checks| {
+            b.iter(|| assert!(verify_batch(checks).is_ok()))
+        });
+
+        group.bench_with_input(BenchmarkId::new("block_is_valid", size), &block, |b, block| {
+            b.iter(|| block.is_valid().unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_signature_verification);
+criterion_main!(benches);
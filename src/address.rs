@@ -0,0 +1,164 @@
+use crate::crypto::PublicKey;
+use crate::errors::{BlockchainError, Result};
+use bech32::primitives::decode::CheckedHrpstring;
+use bech32::{Bech32m, Hrp};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version byte for addresses derived from a single ed25519 public key.
+pub const ADDRESS_VERSION_ED25519: u8 = 0;
+
+/// Version byte for M-of-N multisig addresses.
+pub const ADDRESS_VERSION_MULTISIG: u8 = 1;
+
+/// Version byte for contract addresses, derived from the deployer and nonce.
+pub const ADDRESS_VERSION_CONTRACT: u8 = 2;
+
+const PAYLOAD_LEN: usize = 32;
+
+/// Network an address belongs to, encoded as its human-readable prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Network {
+    #[default]
+    Mainnet,
+    Testnet,
+}
+
+impl Network {
+    pub fn hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "nc",
+            Network::Testnet => "tnc",
+        }
+    }
+
+    fn from_hrp(hrp: &str) -> Option<Self> {
+        match hrp {
+            "nc" => Some(Network::Mainnet),
+            "tnc" => Some(Network::Testnet),
+            _ => None,
+        }
+    }
+}
+
+/// A bech32m-encoded account address: network prefix, version byte and a
+/// 32-byte SHA-256 hash of the key material it was derived from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Address {
+    network: Network,
+    version: u8,
+    payload: [u8; PAYLOAD_LEN],
+}
+
+impl Address {
+    pub fn new(network: Network, version: u8, payload: [u8; PAYLOAD_LEN]) -> Self {
+        Address {
+            network,
+            version,
+            payload,
+        }
+    }
+
+    pub fn from_public_key(public_key: &PublicKey, network: Network) -> Self {
+        let payload: [u8; PAYLOAD_LEN] = Sha256::digest(public_key.to_bytes()).into();
+        Self::new(network, ADDRESS_VERSION_ED25519, payload)
+    }
+
+    /// Address of the contract `deployer` creates with the transaction at
+    /// `nonce`, on the deployer's network.
+    pub fn for_contract(deployer: &str, nonce: u64) -> Self {
+        let network = Self::parse(deployer).map(|address| address.network).unwrap_or_default();
+
+        let mut hasher = Sha256::new();
+        hasher.update(deployer.as_bytes());
+        hasher.update(nonce.to_le_bytes());
+        Self::new(network, ADDRESS_VERSION_CONTRACT, hasher.finalize().into())
+    }
+
+    /// Parses and checksum-verifies an address. Upper-case input is accepted;
+    /// mixed case is not.
+    pub fn parse(address: &str) -> Result<Self> {
+        let checked = CheckedHrpstring::new::<Bech32m>(address).map_err(|e| invalid(address, e))?;
+
+        let hrp = checked.hrp().to_lowercase();
+        let network = Network::from_hrp(&hrp)
+            .ok_or_else(|| invalid(address, format!("unknown network prefix '{}'", hrp)))?;
+
+        let data: Vec<u8> = checked.byte_iter().collect();
+        let (version, payload) = match data.split_first() {
+            Some((version, payload)) if payload.len() == PAYLOAD_LEN => (*version, payload),
+            _ => return Err(invalid(address, "wrong payload length")),
+        };
+
+        if ![ADDRESS_VERSION_ED25519, ADDRESS_VERSION_MULTISIG, ADDRESS_VERSION_CONTRACT].contains(&version) {
+            return Err(invalid(address, format!("unsupported address version {}", version)));
+        }
+
+        let mut bytes = [0u8; PAYLOAD_LEN];
+        bytes.copy_from_slice(payload);
+        Ok(Self::new(network, version, bytes))
+    }
+
+    pub fn network(&self) -> Network {
+        self.network
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn payload(&self) -> &[u8; PAYLOAD_LEN] {
+        &self.payload
+    }
+
+    pub fn is_multisig(&self) -> bool {
+        self.version == ADDRESS_VERSION_MULTISIG
+    }
+
+    pub fn is_contract(&self) -> bool {
+        self.version == ADDRESS_VERSION_CONTRACT
+    }
+}
+
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hrp = Hrp::parse_unchecked(self.network.hrp());
+        let mut data = Vec::with_capacity(1 + PAYLOAD_LEN);
+        data.push(self.version);
+        data.extend_from_slice(&self.payload);
+
+        bech32::encode_lower_to_fmt::<Bech32m, _>(f, hrp, &data).map_err(|_| fmt::Error)
+    }
+}
+
+impl FromStr for Address {
+    type Err = BlockchainError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::parse(s)
+    }
+}
+
+/// Validates a user-supplied address and returns it in canonical (lower-case) form.
+pub fn validate_address(address: &str) -> Result<String> {
+    Address::parse(address.trim()).map(|address| address.to_string())
+}
+
+/// Whether `address` is a well-formed multisig address.
+pub fn is_multisig_address(address: &str) -> bool {
+    Address::parse(address).is_ok_and(|address| address.is_multisig())
+}
+
+/// Id of the contract `deployer` creates with the transaction at `nonce`.
+pub fn contract_address(deployer: &str, nonce: u64) -> String {
+    Address::for_contract(deployer, nonce).to_string()
+}
+
+fn invalid(address: &str, reason: impl fmt::Display) -> BlockchainError {
+    BlockchainError::InvalidAddress {
+        message: format!("'{}': {}", address, reason),
+    }
+}
@@ -1,12 +1,19 @@
+use crate::address::validate_address;
 use crate::block::Block;
 use crate::blockchain::Blockchain;
-use crate::contracts::{ContractCall, ContractEngine, SmartContract};
-use crate::crypto::Wallet;
+use crate::contracts::{
+    ContractABI, ContractCall, ContractEngine, ContractReceipt, ExecutionResult, SmartContract,
+};
+use crate::crypto::{DigitalSignature, PublicKey, Wallet};
 use crate::errors::{BlockchainError, Result};
+use crate::hdwallet::{generate_mnemonic, HdWallet};
+use crate::keystore::{EncryptedKey, Keyring};
+use crate::mempool::{FeeRateBucket, Mempool};
 use crate::mining::{MiningConfig, MiningStats};
-use crate::network::NetworkStats;
+use crate::multisig::{MultisigAccount, MultisigAuthorization, PartialSignature};
+use crate::network::{NetworkStats, P2PEvent};
 use crate::storage::{BlockchainStorage, WalletInfo};
-use crate::transaction::Transaction;
+use crate::transaction::{Transaction, TransferOutput};
 use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
@@ -18,19 +25,21 @@ use base64::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{mpsc, RwLock};
 use tower::ServiceBuilder;
 use tower_http::cors::{Any, CorsLayer};
-use tracing::info;
+use tracing::{info, warn};
 
 #[derive(Clone)]
 pub struct ApiState {
     pub blockchain: Arc<RwLock<Blockchain>>,
     pub storage: Arc<BlockchainStorage>,
     pub contract_engine: Arc<RwLock<ContractEngine>>,
+    pub mempool: Arc<RwLock<Mempool>>,
     pub mining_stats: Arc<RwLock<MiningStats>>,
     pub network_stats: Arc<RwLock<NetworkStats>>,
-    pub wallets: Arc<RwLock<HashMap<String, Wallet>>>,
+    pub keyring: Arc<RwLock<Keyring>>,
+    pub network_events: Option<mpsc::UnboundedSender<P2PEvent>>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -49,26 +58,284 @@ pub struct TransactionRequest {
     pub to: String,
     pub amount: f64,
     pub data: Option<String>,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
+    pub private_key: Option<String>,
+    pub valid_after_height: Option<u64>,
+    pub valid_after_time: Option<chrono::DateTime<chrono::Utc>>,
+    pub expires_at_height: Option<u64>,
+    pub expires_at_time: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BatchTransactionRequest {
+    pub from: String,
+    pub outputs: Vec<TransferOutput>,
+    pub data: Option<String>,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
+    pub private_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CancelTransactionRequest {
+    pub private_key: Option<String>,
+    pub fee: f64,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct KeyRotationRequest {
+    pub new_public_key: String,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
     pub private_key: Option<String>,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct AccountKeyView {
+    pub address: String,
+    /// Current signing key, or `None` if the account never rotated and still
+    /// signs with the key its address was derived from.
+    pub authorized_key: Option<String>,
+    pub rotated: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct TransactionSubmission {
+    pub transaction: Transaction,
+    pub replaced_transaction_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MempoolInfo {
+    pub count: usize,
+    pub total_size: usize,
+    pub total_fees: f64,
+    pub fee_rate_histogram: Vec<FeeRateBucket>,
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AddressMempoolView {
+    pub address: String,
+    pub confirmed_balance: f64,
+    pub projected_balance: f64,
+    pub next_nonce: u64,
+    pub outgoing: Vec<Transaction>,
+    pub incoming: Vec<Transaction>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct MineBlockRequest {
     pub miner_address: String,
     pub include_pending: bool,
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct MinedBlock {
+    pub block: Block,
+    pub contract_receipts: Vec<ContractReceipt>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct CreateWalletRequest {
     pub name: String,
+    pub password: String,
+}
+
+/// Public view of a wallet; never carries key material.
+#[derive(Serialize, Deserialize)]
+pub struct WalletView {
+    pub name: String,
+    pub address: String,
+    pub public_key: String,
+    pub balance: f64,
+}
+
+impl WalletView {
+    pub fn from_wallet(wallet: &Wallet, balance: f64) -> Self {
+        WalletView {
+            name: wallet.name.clone(),
+            address: wallet.address(),
+            public_key: wallet.public_key().to_string(),
+            balance,
+        }
+    }
+
+    pub fn from_info(info: WalletInfo, balance: f64) -> Self {
+        WalletView {
+            name: info.name,
+            address: info.address,
+            public_key: info.public_key,
+            balance,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExportWalletRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WalletExport {
+    pub name: String,
+    pub address: String,
+    pub public_key: String,
+    pub keystore: EncryptedKey,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct UnlockWalletRequest {
+    pub password: String,
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct WalletUnlockStatus {
+    pub address: String,
+    pub unlocked_until: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateHdWalletRequest {
+    pub name: String,
+    pub password: String,
+    #[serde(default = "default_word_count")]
+    pub word_count: usize,
+}
+
+fn default_word_count() -> usize {
+    24
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct RestoreHdWalletRequest {
+    pub name: String,
+    pub mnemonic: String,
+    pub password: String,
+    #[serde(default = "default_restore_accounts")]
+    pub accounts: u32,
+}
+
+fn default_restore_accounts() -> u32 {
+    1
+}
+
+const MAX_RESTORE_ACCOUNTS: u32 = 100;
+
+#[derive(Serialize, Deserialize)]
+pub struct DeriveAccountRequest {
+    pub password: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct DerivedAccount {
+    pub index: u32,
+    pub derivation_path: String,
+    pub wallet: WalletView,
+}
+
+/// Returned once when an HD wallet is created; the mnemonic is not stored in
+/// clear text and cannot be retrieved again.
+#[derive(Serialize, Deserialize)]
+pub struct HdWalletCreated {
+    pub name: String,
+    pub mnemonic: String,
+    pub account: DerivedAccount,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct HdWalletRestored {
+    pub name: String,
+    pub accounts: Vec<DerivedAccount>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CreateMultisigRequest {
+    pub threshold: usize,
+    pub public_keys: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MultisigAccountView {
+    pub address: String,
+    pub threshold: usize,
+    pub public_keys: Vec<String>,
+}
+
+impl MultisigAccountView {
+    pub fn from_account(account: &MultisigAccount) -> Self {
+        MultisigAccountView {
+            address: account.address(),
+            threshold: account.threshold,
+            public_keys: account.public_keys.iter().map(|key| key.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct MultisigProposalRequest {
+    pub to: String,
+    pub amount: f64,
+    pub data: Option<String>,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
+}
+
+/// A multisig transaction collecting signatures. Co-signers sign
+/// `signable_data` (hex) and post the result back.
+#[derive(Serialize, Deserialize)]
+pub struct MultisigProposal {
+    pub transaction: Transaction,
+    pub signable_data: String,
+    pub signatures: usize,
+    pub threshold: usize,
+    pub complete: bool,
+}
+
+impl MultisigProposal {
+    fn from_transaction(transaction: Transaction) -> Result<Self> {
+        let multisig = transaction.multisig.as_ref().ok_or_else(|| BlockchainError::InvalidTransaction {
+            message: "Transaction is not a multisig transaction".to_string(),
+        })?;
+
+        Ok(MultisigProposal {
+            signable_data: hex::encode(transaction.signable_data()?),
+            signatures: multisig.signatures.len(),
+            threshold: multisig.account.threshold,
+            complete: multisig.is_complete(),
+            transaction,
+        })
+    }
+}
+
+/// Either a detached `signature` from `public_key`, or the address of a
+/// co-signer wallet unlocked in the node to sign with.
+#[derive(Serialize, Deserialize)]
+pub struct PartialSignatureRequest {
+    pub public_key: Option<String>,
+    pub signature: Option<String>,
+    pub signer: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct ContractDeployRequest {
     pub name: String,
     pub code: String, // Base64 encoded WASM
+    /// Taken from the module's `abi` custom section if omitted.
+    #[serde(default)]
+    pub abi: Option<ContractABI>,
     pub owner: String,
     pub gas_limit: u64,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
+    pub private_key: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -79,6 +346,21 @@ pub struct ContractCallRequest {
     pub caller: String,
     pub value: f64,
     pub gas_limit: u64,
+    #[serde(default)]
+    pub fee: f64,
+    pub nonce: Option<u64>,
+    pub private_key: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ContractViewRequest {
+    pub function_name: String,
+    #[serde(default)]
+    pub args: Vec<serde_json::Value>,
+    /// Address reported to the contract as the caller.
+    pub caller: Option<String>,
+    /// Defaults to the smaller of the contract's limit and `max_gas_limit`.
+    pub gas_limit: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -106,6 +388,13 @@ impl<T> ApiResponse<T> {
     }
 }
 
+/// Validates an address taken from a request, returning its canonical form or
+/// a `400` response.
+fn parse_address<T>(address: &str) -> std::result::Result<String, (StatusCode, Json<ApiResponse<T>>)> {
+    validate_address(address)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiResponse::error(e.to_string()))))
+}
+
 pub fn create_router(state: ApiState) -> Router {
     Router::new()
         // Blockchain endpoints
@@ -117,8 +406,17 @@ pub fn create_router(state: ApiState) -> Router {
         // Transaction endpoints
         .route("/api/transactions", get(get_transactions))
         .route("/api/transactions", post(create_transaction))
+        .route("/api/transactions/batch", post(create_batch_transaction))
+        .route("/api/transactions/signed", post(submit_signed_transaction))
         .route("/api/transactions/:id", get(get_transaction))
+        .route("/api/transactions/:id/cancel", post(cancel_transaction))
         .route("/api/balance/:address", get(get_balance))
+        .route("/api/accounts/:address/key", get(get_account_key))
+        .route("/api/accounts/:address/rotate-key", post(rotate_account_key))
+
+        // Mempool endpoints
+        .route("/api/mempool", get(get_mempool))
+        .route("/api/mempool/:address", get(get_address_mempool))
 
         // Mining endpoints
         .route("/api/mine", post(mine_block))
@@ -130,12 +428,27 @@ pub fn create_router(state: ApiState) -> Router {
         .route("/api/wallets", get(list_wallets))
         .route("/api/wallets", post(create_wallet))
         .route("/api/wallets/:address", get(get_wallet))
+        .route("/api/wallets/:address/unlock", post(unlock_wallet))
+        .route("/api/wallets/:address/lock", post(lock_wallet))
+        .route("/api/wallets/:address/export", post(export_wallet))
+        .route("/api/hd-wallets", post(create_hd_wallet))
+        .route("/api/hd-wallets/restore", post(restore_hd_wallet))
+        .route("/api/hd-wallets/:name/derive", post(derive_hd_account))
+
+        // Multisig endpoints
+        .route("/api/multisig", post(create_multisig))
+        .route("/api/multisig/:address", get(get_multisig))
+        .route("/api/multisig/:address/proposals", post(propose_multisig_transaction))
+        .route("/api/multisig/proposals/:id", get(get_multisig_proposal))
+        .route("/api/multisig/proposals/:id/signatures", post(sign_multisig_proposal))
+        .route("/api/multisig/proposals/:id/submit", post(submit_multisig_proposal))
 
         // Smart contract endpoints
         .route("/api/contracts", get(list_contracts))
         .route("/api/contracts", post(deploy_contract))
         .route("/api/contracts/:id", get(get_contract))
         .route("/api/contracts/:id/call", post(call_contract))
+        .route("/api/contracts/:id/view", post(view_contract))
 
         // Network endpoints
         .route("/api/network/stats", get(get_network_stats))
@@ -231,12 +544,30 @@ async fn get_transactions(
     State(state): State<ApiState>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    let blockchain = state.blockchain.read().await;
-
     let limit = params.get("limit")
         .and_then(|l| l.parse::<usize>().ok())
         .unwrap_or(20);
 
+    // An address's history comes from the storage index, which also finds
+    // batch transfers through their outputs
+    if let Some(address) = params.get("address") {
+        let address = match parse_address::<Vec<Transaction>>(address) {
+            Ok(address) => address,
+            Err(response) => return response,
+        };
+        return match state.storage.load_address_transactions(&address).await {
+            Ok(history) => {
+                let transactions: Vec<Transaction> = history.into_iter().rev().take(limit).collect();
+                (StatusCode::OK, Json(ApiResponse::success(transactions)))
+            }
+            Err(e) => {
+                let response = ApiResponse::<Vec<Transaction>>::error(e.to_string());
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+            }
+        };
+    }
+
+    let blockchain = state.blockchain.read().await;
     let transactions: Vec<Transaction> = blockchain.chain()
         .iter()
         .rev()
@@ -245,65 +576,229 @@ async fn get_transactions(
         .cloned()
         .collect();
 
-    Json(ApiResponse::success(transactions))
+    (StatusCode::OK, Json(ApiResponse::success(transactions)))
 }
 
 async fn create_transaction(
     State(state): State<ApiState>,
     Json(req): Json<TransactionRequest>,
 ) -> impl IntoResponse {
-    let _wallets = state.wallets.read().await;
-
-    let transaction = if let Some(private_key_hex) = req.private_key {
-        // Create signed transaction
-        let private_key = match hex::decode(private_key_hex) {
-            Ok(key) => key,
-            Err(_) => {
-                let response = ApiResponse::<Transaction>::error("Invalid private key format".to_string());
-                return (StatusCode::BAD_REQUEST, Json(response));
-            }
-        };
+    let from = match parse_address(&req.from) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let to = match parse_address(&req.to) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
 
-        let wallet = match Wallet::from_private_key("temp".to_string(), &private_key) {
-            Ok(w) => w,
-            Err(e) => {
-                let response = ApiResponse::<Transaction>::error(e.to_string());
-                return (StatusCode::BAD_REQUEST, Json(response));
-            }
-        };
+    let transaction = Transaction::new(from, to, req.amount, req.data).map(|mut tx| {
+        tx.valid_after_height = req.valid_after_height;
+        tx.valid_after_time = req.valid_after_time;
+        tx.expires_at_height = req.expires_at_height;
+        tx.expires_at_time = req.expires_at_time;
+        tx
+    });
+    sign_and_submit(&state, transaction, req.fee, req.nonce, req.private_key.as_deref()).await
+}
 
-        let mut tx = match Transaction::new(req.from, req.to, req.amount, req.data) {
-            Ok(t) => t,
-            Err(e) => {
-                let response = ApiResponse::<Transaction>::error(e.to_string());
-                return (StatusCode::BAD_REQUEST, Json(response));
-            }
-        };
+async fn create_batch_transaction(
+    State(state): State<ApiState>,
+    Json(req): Json<BatchTransactionRequest>,
+) -> impl IntoResponse {
+    let from = match parse_address(&req.from) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let mut outputs = Vec::with_capacity(req.outputs.len());
+    for output in req.outputs {
+        match parse_address(&output.to) {
+            Ok(to) => outputs.push(TransferOutput { to, amount: output.amount }),
+            Err(response) => return response,
+        }
+    }
 
-        let signable_data = match tx.signable_data() {
-            Ok(data) => data,
-            Err(e) => {
-                let response = ApiResponse::<Transaction>::error(e.to_string());
-                return (StatusCode::BAD_REQUEST, Json(response));
+    let transaction = Transaction::batch_transfer(from, outputs, req.data);
+    sign_and_submit(&state, transaction, req.fee, req.nonce, req.private_key.as_deref()).await
+}
+
+/// Sets the fee and nonce (defaulting to the sender's next one) on a
+/// transaction built by a handler, then signs and submits it.
+async fn sign_and_submit(
+    state: &ApiState,
+    transaction: Result<Transaction>,
+    fee: f64,
+    nonce: Option<u64>,
+    private_key: Option<&str>,
+) -> (StatusCode, Json<ApiResponse<TransactionSubmission>>) {
+    let mut transaction = match transaction {
+        Ok(tx) => tx,
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+    transaction.fee = fee;
+    let nonce = match nonce {
+        Some(nonce) => nonce,
+        None => {
+            let blockchain = state.blockchain.read().await;
+            state.mempool.read().await.pending_nonce(&transaction.from, &blockchain)
+        }
+    };
+    transaction.set_nonce(nonce);
+
+    let transaction = match sign_transaction(state, transaction, private_key).await {
+        Ok(tx) => tx,
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+
+    let blockchain = state.blockchain.read().await;
+    let mut mempool = state.mempool.write().await;
+    submit_transaction(state, &blockchain, &mut mempool, transaction)
+}
+
+async fn cancel_transaction(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(req): Json<CancelTransactionRequest>,
+) -> impl IntoResponse {
+    let original = match state.mempool.read().await.get_transaction(&id) {
+        Some(tx) => tx.clone(),
+        None => {
+            let response = ApiResponse::<TransactionSubmission>::error("Pending transaction not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+    };
+
+    let cancellation = match Transaction::cancellation(&original, req.fee) {
+        Ok(tx) => tx,
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+
+    let cancellation = match sign_transaction(&state, cancellation, req.private_key.as_deref()).await {
+        Ok(tx) => tx,
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+
+    let blockchain = state.blockchain.read().await;
+    let mut mempool = state.mempool.write().await;
+    submit_transaction(&state, &blockchain, &mut mempool, cancellation)
+}
+
+/// Accepts a transaction signed elsewhere (e.g. offline with the CLI). The
+/// signature must verify before the transaction reaches the mempool.
+async fn submit_signed_transaction(
+    State(state): State<ApiState>,
+    Json(transaction): Json<Transaction>,
+) -> impl IntoResponse {
+    if let Err(response) = parse_address::<TransactionSubmission>(&transaction.from) {
+        return response;
+    }
+    if !transaction.kind.targets_contract() {
+        for (to, _) in transaction.credits() {
+            if let Err(response) = parse_address::<TransactionSubmission>(to) {
+                return response;
             }
-        };
+        }
+    }
 
-        tx.signature = Some(wallet.sign_transaction(&signable_data));
-        tx.from_public_key = Some(wallet.keypair.public_key().clone());
-        tx
-    } else {
-        // Create unsigned transaction
-        match Transaction::new(req.from, req.to, req.amount, req.data) {
-            Ok(tx) => tx,
-            Err(e) => {
-                let response = ApiResponse::<Transaction>::error(e.to_string());
-                return (StatusCode::BAD_REQUEST, Json(response));
+    if transaction.signature.is_none() && transaction.multisig.is_none() {
+        let response = ApiResponse::<TransactionSubmission>::error("Transaction is not signed".to_string());
+        return (StatusCode::BAD_REQUEST, Json(response));
+    }
+
+    if !transaction.verify_signature() {
+        let response = ApiResponse::<TransactionSubmission>::error("Invalid transaction signature".to_string());
+        return (StatusCode::BAD_REQUEST, Json(response));
+    }
+
+    let blockchain = state.blockchain.read().await;
+    let mut mempool = state.mempool.write().await;
+
+    submit_transaction(&state, &blockchain, &mut mempool, transaction)
+}
+
+/// Signs with the supplied private key, or with the wallet holding the sender's
+/// current key if it is unlocked in the node. Transactions with neither are
+/// returned unsigned. Call this without holding the chain or mempool locks:
+/// a remote signer may take seconds to answer.
+async fn sign_transaction(
+    state: &ApiState,
+    mut transaction: Transaction,
+    private_key_hex: Option<&str>,
+) -> Result<Transaction> {
+    let wallet = match private_key_hex {
+        Some(private_key_hex) => {
+            let private_key = hex::decode(private_key_hex).map_err(|_| BlockchainError::InvalidTransaction {
+                message: "Invalid private key format".to_string(),
+            })?;
+            Wallet::from_private_key("temp".to_string(), &private_key)?
+        }
+        None => {
+            let signer = signer_address(&*state.blockchain.read().await, &transaction.from);
+            match state.keyring.write().await.wallet(&signer) {
+                Some(wallet) => wallet.clone(),
+                None => return Ok(transaction),
             }
         }
     };
 
-    // In a real implementation, you'd add this to a transaction pool
-    (StatusCode::OK, Json(ApiResponse::success(transaction)))
+    tokio::task::spawn_blocking(move || {
+        transaction.sign(&wallet)?;
+        Ok(transaction)
+    })
+    .await
+    .map_err(std::io::Error::from)?
+}
+
+/// Address of the wallet holding `address`'s current signing key.
+fn signer_address(blockchain: &Blockchain, address: &str) -> String {
+    blockchain
+        .authorized_key(address)
+        .map(|key| key.to_address())
+        .unwrap_or_else(|| address.to_string())
+}
+
+fn submit_transaction(
+    state: &ApiState,
+    blockchain: &Blockchain,
+    mempool: &mut Mempool,
+    transaction: Transaction,
+) -> (StatusCode, Json<ApiResponse<TransactionSubmission>>) {
+    match mempool.add_transaction(transaction.clone(), blockchain) {
+        Ok(replaced) => {
+            if let Some(sender) = &state.network_events {
+                let event = match &replaced {
+                    Some(old) => P2PEvent::TransactionReplaced {
+                        replaced_id: old.id.clone(),
+                        replacement: transaction.clone(),
+                    },
+                    None => P2PEvent::NewTransaction(transaction.clone()),
+                };
+                let _ = sender.send(event);
+            }
+
+            let submission = TransactionSubmission {
+                transaction,
+                replaced_transaction_id: replaced.map(|tx| tx.id),
+            };
+            (StatusCode::OK, Json(ApiResponse::success(submission)))
+        }
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            (StatusCode::BAD_REQUEST, Json(response))
+        }
+    }
 }
 
 async fn get_transaction(State(state): State<ApiState>, Path(id): Path<String>) -> impl IntoResponse {
@@ -322,82 +817,697 @@ async fn get_transaction(State(state): State<ApiState>, Path(id): Path<String>)
 }
 
 async fn get_balance(State(state): State<ApiState>, Path(address): Path<String>) -> impl IntoResponse {
+    let address = match parse_address::<f64>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
     let blockchain = state.blockchain.read().await;
     let balance = blockchain.get_balance(&address);
-    Json(ApiResponse::success(balance))
-}
-
-// Mining API handlers
-async fn mine_block(
-    State(_state): State<ApiState>,
-    Json(_req): Json<MineBlockRequest>
-) -> impl IntoResponse {
-    // This is a simplified mining endpoint
-    // In a real implementation, mining would happen in background threads
-    Json(ApiResponse::success("Mining started"))
+    (StatusCode::OK, Json(ApiResponse::success(balance)))
 }
 
-async fn get_mining_stats(State(state): State<ApiState>) -> impl IntoResponse {
-    let stats = state.mining_stats.read().await;
-    Json(ApiResponse::success(stats.clone()))
-}
+async fn get_account_key(State(state): State<ApiState>, Path(address): Path<String>) -> impl IntoResponse {
+    let address = match parse_address::<AccountKeyView>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
 
-async fn get_mining_config(State(_state): State<ApiState>) -> impl IntoResponse {
-    let config = MiningConfig::default();
-    Json(ApiResponse::success(config))
+    let blockchain = state.blockchain.read().await;
+    let authorized_key = blockchain.authorized_key(&address).map(|key| key.to_string());
+    let view = AccountKeyView {
+        address,
+        rotated: authorized_key.is_some(),
+        authorized_key,
+    };
+    (StatusCode::OK, Json(ApiResponse::success(view)))
 }
 
-async fn update_mining_config(
-    State(_state): State<ApiState>,
-    Json(_config): Json<MiningConfig>,
+/// Submits a key rotation for `address`, signed by its current key.
+async fn rotate_account_key(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Json(req): Json<KeyRotationRequest>,
 ) -> impl IntoResponse {
-    // Update mining configuration
-    Json(ApiResponse::success("Mining configuration updated"))
-}
+    let address = match parse_address(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
 
-// Wallet API handlers
-async fn list_wallets(State(state): State<ApiState>) -> impl IntoResponse {
-    match state.storage.list_wallets().await {
-        Ok(wallets) => (StatusCode::OK, Json(ApiResponse::success(wallets))),
-        Err(e) => {
-            let response = ApiResponse::<Vec<WalletInfo>>::error(e.to_string());
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
-        }
-    }
+    let transaction = PublicKey::from_hex(&req.new_public_key)
+        .and_then(|new_public_key| Transaction::key_rotation(address, new_public_key));
+    sign_and_submit(&state, transaction, req.fee, req.nonce, req.private_key.as_deref()).await
 }
 
-async fn create_wallet(
+// Mempool API handlers
+async fn get_mempool(
     State(state): State<ApiState>,
-    Json(req): Json<CreateWalletRequest>,
+    Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    let wallet = Wallet::new(req.name);
+    let mempool = state.mempool.read().await;
 
-    match state.storage.save_wallet(&wallet).await {
+    let limit = params.get("limit")
+        .and_then(|l| l.parse::<usize>().ok())
+        .unwrap_or(20);
+
+    let offset = params.get("offset")
+        .and_then(|o| o.parse::<usize>().ok())
+        .unwrap_or(0);
+
+    let info = MempoolInfo {
+        count: mempool.len(),
+        total_size: mempool.total_size(),
+        total_fees: mempool.total_fees(),
+        fee_rate_histogram: mempool.fee_rate_histogram(),
+        transactions: mempool.transactions_by_fee_rate()
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .cloned()
+            .collect(),
+    };
+
+    Json(ApiResponse::success(info))
+}
+
+async fn get_address_mempool(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+) -> impl IntoResponse {
+    let address = match parse_address::<AddressMempoolView>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let blockchain = state.blockchain.read().await;
+    let mempool = state.mempool.read().await;
+
+    let (outgoing, incoming) = mempool.transactions_for_address(&address);
+    let view = AddressMempoolView {
+        confirmed_balance: blockchain.get_balance(&address),
+        projected_balance: mempool.projected_balance(&address, &blockchain),
+        next_nonce: mempool.pending_nonce(&address, &blockchain),
+        outgoing: outgoing.into_iter().cloned().collect(),
+        incoming: incoming.into_iter().cloned().collect(),
+        address,
+    };
+
+    (StatusCode::OK, Json(ApiResponse::success(view)))
+}
+
+// Mining API handlers
+/// Appends a block paying the reward to `miner_address` and, with
+/// `include_pending`, confirming the mempool's ready transactions. Those that
+/// can no longer be included are dropped from the mempool. Contract
+/// transactions in the block are then executed.
+async fn mine_block(
+    State(state): State<ApiState>,
+    Json(req): Json<MineBlockRequest>
+) -> impl IntoResponse {
+    let miner_address = match parse_address::<MinedBlock>(&req.miner_address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let mut blockchain = state.blockchain.write().await;
+    let mut mempool = state.mempool.write().await;
+
+    let height = blockchain.next_index();
+    let mut transactions: Vec<Transaction> = if req.include_pending {
+        let ready = mempool.ready_transactions(&blockchain, chrono::Utc::now());
+        let (selected, rejected) = blockchain.select_transactions(ready);
+        for (id, e) in rejected {
+            warn!("Dropping transaction {} from the mempool: {}", id, e);
+            mempool.remove_transaction(&id);
+        }
+        selected
+    } else {
+        Vec::new()
+    };
+    let reward = MiningConfig::default().block_reward + transactions.iter().map(|tx| tx.fee).sum::<f64>();
+
+    let result = Transaction::coinbase(miner_address, reward).and_then(|coinbase| {
+        transactions.insert(0, coinbase);
+        blockchain.prepare_block(transactions)
+    });
+    let prepared = match result {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            let response = ApiResponse::<MinedBlock>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+    let block = prepared.block().clone();
+
+    // The block and its contract changes are committed only once both are
+    // persisted, so a failed save leaves the node as it was
+    let contract_receipts = {
+        let mut engine = state.contract_engine.write().await;
+        let receipts = engine.apply_block(&block);
+        if let Err(e) = state.storage.save_block_with_contracts(&block, &receipts, engine.changes()).await {
+            engine.revert_changes();
+            warn!("Failed to persist block #{}: {}", block.index, e);
+            let response = ApiResponse::<MinedBlock>::error(format!("Failed to persist block: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+        engine.take_changes();
+        receipts
+    };
+    blockchain.commit_block(prepared);
+    blockchain.record_contract_receipts(&contract_receipts);
+    mempool.remove_confirmed(&block);
+    mempool.remove_expired(height + 1, chrono::Utc::now());
+
+    info!("Block #{} mined with {} transactions", block.index, block.transactions.len());
+    let mined = MinedBlock {
+        block,
+        contract_receipts,
+    };
+    (StatusCode::OK, Json(ApiResponse::success(mined)))
+}
+
+async fn get_mining_stats(State(state): State<ApiState>) -> impl IntoResponse {
+    let stats = state.mining_stats.read().await;
+    Json(ApiResponse::success(stats.clone()))
+}
+
+async fn get_mining_config(State(_state): State<ApiState>) -> impl IntoResponse {
+    let config = MiningConfig::default();
+    Json(ApiResponse::success(config))
+}
+
+async fn update_mining_config(
+    State(_state): State<ApiState>,
+    Json(_config): Json<MiningConfig>,
+) -> impl IntoResponse {
+    // Update mining configuration
+    Json(ApiResponse::success("Mining configuration updated"))
+}
+
+// Wallet API handlers
+async fn list_wallets(State(state): State<ApiState>) -> impl IntoResponse {
+    match state.storage.list_wallets().await {
+        Ok(wallets) => (StatusCode::OK, Json(ApiResponse::success(wallets))),
+        Err(e) => {
+            let response = ApiResponse::<Vec<WalletInfo>>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn create_wallet(
+    State(state): State<ApiState>,
+    Json(req): Json<CreateWalletRequest>,
+) -> impl IntoResponse {
+    let wallet = Wallet::new(req.name);
+
+    match state.storage.save_wallet(&wallet, &req.password).await {
         Ok(()) => {
-            state.wallets.write().await.insert(wallet.address(), wallet.clone());
-            (StatusCode::OK, Json(ApiResponse::success(wallet)))
+            let balance = state.blockchain.read().await.get_balance(&wallet.address());
+            (StatusCode::OK, Json(ApiResponse::success(WalletView::from_wallet(&wallet, balance))))
+        }
+        Err(e @ BlockchainError::Wallet { .. }) => {
+            let response = ApiResponse::<WalletView>::error(e.to_string());
+            (StatusCode::BAD_REQUEST, Json(response))
         }
         Err(e) => {
-            let response = ApiResponse::<Wallet>::error(e.to_string());
+            let response = ApiResponse::<WalletView>::error(e.to_string());
             (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
         }
     }
 }
 
 async fn get_wallet(State(state): State<ApiState>, Path(address): Path<String>) -> impl IntoResponse {
-    match state.storage.load_wallet(&address).await {
-        Ok(Some(wallet)) => (StatusCode::OK, Json(ApiResponse::success(wallet))),
+    let address = match parse_address::<WalletView>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    match state.storage.get_wallet_info(&address).await {
+        Ok(Some(info)) => {
+            let balance = state.blockchain.read().await.get_balance(&address);
+            (StatusCode::OK, Json(ApiResponse::success(WalletView::from_info(info, balance))))
+        }
+        Ok(None) => {
+            let response = ApiResponse::<WalletView>::error("Wallet not found".to_string());
+            (StatusCode::NOT_FOUND, Json(response))
+        }
+        Err(e) => {
+            let response = ApiResponse::<WalletView>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+/// Returns the encrypted keystore blob once the caller proves they know the
+/// wallet password. The private key itself never leaves the database.
+async fn export_wallet(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Json(req): Json<ExportWalletRequest>,
+) -> impl IntoResponse {
+    let address = match parse_address::<WalletExport>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let info = match state.storage.get_wallet_info(&address).await {
+        Ok(Some(info)) => info,
+        Ok(None) => {
+            let response = ApiResponse::<WalletExport>::error("Wallet not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+        Err(e) => {
+            let response = ApiResponse::<WalletExport>::error(e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+    };
+
+    let keystore = match state.storage.load_encrypted_key(&address).await {
+        Ok(Some(keystore)) => keystore,
+        Ok(None) => {
+            let response = ApiResponse::<WalletExport>::error("Wallet not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+        Err(e) => {
+            let response = ApiResponse::<WalletExport>::error(e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+    };
+
+    if let Err(e) = keystore.decrypt(&req.password) {
+        let response = ApiResponse::<WalletExport>::error(e.to_string());
+        return (StatusCode::UNAUTHORIZED, Json(response));
+    }
+
+    let export = WalletExport {
+        name: info.name,
+        address: info.address,
+        public_key: info.public_key,
+        keystore,
+    };
+    (StatusCode::OK, Json(ApiResponse::success(export)))
+}
+
+async fn unlock_wallet(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Json(req): Json<UnlockWalletRequest>,
+) -> impl IntoResponse {
+    let address = match parse_address::<WalletUnlockStatus>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let wallet = match state.storage.load_wallet(&address, &req.password).await {
+        Ok(Some(wallet)) => wallet,
+        Ok(None) => {
+            let response = ApiResponse::<WalletUnlockStatus>::error("Wallet not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+        Err(e @ BlockchainError::Wallet { .. }) => {
+            let response = ApiResponse::<WalletUnlockStatus>::error(e.to_string());
+            return (StatusCode::UNAUTHORIZED, Json(response));
+        }
+        Err(e) => {
+            let response = ApiResponse::<WalletUnlockStatus>::error(e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+    };
+
+    let timeout = state.keyring.write().await.unlock(
+        wallet,
+        req.timeout_secs.map(std::time::Duration::from_secs),
+    );
+
+    let status = WalletUnlockStatus {
+        address,
+        unlocked_until: chrono::Utc::now()
+            + chrono::Duration::from_std(timeout).unwrap_or_default(),
+    };
+    (StatusCode::OK, Json(ApiResponse::success(status)))
+}
+
+async fn lock_wallet(State(state): State<ApiState>, Path(address): Path<String>) -> impl IntoResponse {
+    let address = match parse_address::<&str>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    if state.keyring.write().await.lock(&address) {
+        (StatusCode::OK, Json(ApiResponse::success("Wallet locked")))
+    } else {
+        let response = ApiResponse::<&str>::error("Wallet is not unlocked".to_string());
+        (StatusCode::NOT_FOUND, Json(response))
+    }
+}
+
+impl DerivedAccount {
+    fn new(index: u32, wallet: &Wallet, balance: f64) -> Self {
+        DerivedAccount {
+            index,
+            derivation_path: HdWallet::derivation_path(index),
+            wallet: WalletView::from_wallet(wallet, balance),
+        }
+    }
+}
+
+async fn create_hd_wallet(
+    State(state): State<ApiState>,
+    Json(req): Json<CreateHdWalletRequest>,
+) -> impl IntoResponse {
+    let result = async {
+        let mnemonic = generate_mnemonic(req.word_count)?;
+        let mut hd_wallet = HdWallet::from_mnemonic(req.name, &mnemonic)?;
+        let (index, wallet) = hd_wallet.derive_next()?;
+
+        state.storage.save_hd_wallet(&hd_wallet, &req.password).await?;
+        state.storage.save_wallet(&wallet, &req.password).await?;
+
+        Ok::<_, BlockchainError>((hd_wallet.name, mnemonic, index, wallet))
+    }
+    .await;
+
+    match result {
+        Ok((name, mnemonic, index, wallet)) => {
+            let balance = state.blockchain.read().await.get_balance(&wallet.address());
+            let created = HdWalletCreated {
+                name,
+                mnemonic,
+                account: DerivedAccount::new(index, &wallet, balance),
+            };
+            (StatusCode::OK, Json(ApiResponse::success(created)))
+        }
+        Err(e @ BlockchainError::Wallet { .. }) => {
+            let response = ApiResponse::<HdWalletCreated>::error(e.to_string());
+            (StatusCode::BAD_REQUEST, Json(response))
+        }
+        Err(e) => {
+            let response = ApiResponse::<HdWalletCreated>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn derive_hd_account(
+    State(state): State<ApiState>,
+    Path(name): Path<String>,
+    Json(req): Json<DeriveAccountRequest>,
+) -> impl IntoResponse {
+    match state.storage.derive_hd_account(&name, &req.password).await {
+        Ok(Some((index, wallet))) => {
+            let balance = state.blockchain.read().await.get_balance(&wallet.address());
+            (StatusCode::OK, Json(ApiResponse::success(DerivedAccount::new(index, &wallet, balance))))
+        }
+        Ok(None) => {
+            let response = ApiResponse::<DerivedAccount>::error("HD wallet not found".to_string());
+            (StatusCode::NOT_FOUND, Json(response))
+        }
+        Err(e @ BlockchainError::Wallet { .. }) => {
+            let response = ApiResponse::<DerivedAccount>::error(e.to_string());
+            (StatusCode::UNAUTHORIZED, Json(response))
+        }
+        Err(e) => {
+            let response = ApiResponse::<DerivedAccount>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn restore_hd_wallet(
+    State(state): State<ApiState>,
+    Json(req): Json<RestoreHdWalletRequest>,
+) -> impl IntoResponse {
+    let result = async {
+        if req.accounts > MAX_RESTORE_ACCOUNTS {
+            return Err(BlockchainError::Wallet {
+                message: format!("Cannot restore more than {} accounts at once", MAX_RESTORE_ACCOUNTS),
+            });
+        }
+
+        let mut hd_wallet = HdWallet::from_mnemonic(req.name, &req.mnemonic)?;
+        let mut wallets = Vec::new();
+        for _ in 0..req.accounts.max(1) {
+            wallets.push(hd_wallet.derive_next()?);
+        }
+
+        state.storage.save_hd_wallet(&hd_wallet, &req.password).await?;
+        for (_, wallet) in &wallets {
+            state.storage.save_wallet(wallet, &req.password).await?;
+        }
+
+        Ok::<_, BlockchainError>((hd_wallet.name, wallets))
+    }
+    .await;
+
+    match result {
+        Ok((name, wallets)) => {
+            let blockchain = state.blockchain.read().await;
+            let accounts = wallets
+                .iter()
+                .map(|(index, wallet)| {
+                    DerivedAccount::new(*index, wallet, blockchain.get_balance(&wallet.address()))
+                })
+                .collect();
+            (StatusCode::OK, Json(ApiResponse::success(HdWalletRestored { name, accounts })))
+        }
+        Err(e @ BlockchainError::Wallet { .. }) => {
+            let response = ApiResponse::<HdWalletRestored>::error(e.to_string());
+            (StatusCode::BAD_REQUEST, Json(response))
+        }
+        Err(e) => {
+            let response = ApiResponse::<HdWalletRestored>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+// Multisig API handlers
+async fn create_multisig(
+    State(state): State<ApiState>,
+    Json(req): Json<CreateMultisigRequest>,
+) -> impl IntoResponse {
+    let account = match req
+        .public_keys
+        .iter()
+        .map(|key| PublicKey::from_hex(key))
+        .collect::<Result<Vec<_>>>()
+        .and_then(|keys| MultisigAccount::new(req.threshold, keys))
+    {
+        Ok(account) => account,
+        Err(e) => {
+            let response = ApiResponse::<MultisigAccountView>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+
+    match state.storage.save_multisig_account(&account).await {
+        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(MultisigAccountView::from_account(&account)))),
+        Err(e) => {
+            let response = ApiResponse::<MultisigAccountView>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn get_multisig(State(state): State<ApiState>, Path(address): Path<String>) -> impl IntoResponse {
+    let address = match parse_address::<MultisigAccountView>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    match state.storage.load_multisig_account(&address).await {
+        Ok(Some(account)) => (StatusCode::OK, Json(ApiResponse::success(MultisigAccountView::from_account(&account)))),
         Ok(None) => {
-            let response = ApiResponse::<Wallet>::error("Wallet not found".to_string());
+            let response = ApiResponse::<MultisigAccountView>::error("Multisig account not found".to_string());
             (StatusCode::NOT_FOUND, Json(response))
         }
         Err(e) => {
-            let response = ApiResponse::<Wallet>::error(e.to_string());
+            let response = ApiResponse::<MultisigAccountView>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn propose_multisig_transaction(
+    State(state): State<ApiState>,
+    Path(address): Path<String>,
+    Json(req): Json<MultisigProposalRequest>,
+) -> impl IntoResponse {
+    let address = match parse_address::<MultisigProposal>(&address) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+    let to = match parse_address::<MultisigProposal>(&req.to) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let account = match state.storage.load_multisig_account(&address).await {
+        Ok(Some(account)) => account,
+        Ok(None) => {
+            let response = ApiResponse::<MultisigProposal>::error("Multisig account not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+        Err(e) => {
+            let response = ApiResponse::<MultisigProposal>::error(e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+    };
+
+    let mut transaction = match Transaction::new(address, to, req.amount, req.data) {
+        Ok(tx) => tx,
+        Err(e) => {
+            let response = ApiResponse::<MultisigProposal>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+    transaction.fee = req.fee;
+    transaction.nonce = match req.nonce {
+        Some(nonce) => nonce,
+        None => {
+            let blockchain = state.blockchain.read().await;
+            state.mempool.read().await.pending_nonce(&transaction.from, &blockchain)
+        }
+    };
+    transaction.multisig = Some(MultisigAuthorization::new(account));
+
+    let result = async {
+        state.storage.save_multisig_proposal(&transaction).await?;
+        MultisigProposal::from_transaction(transaction)
+    }
+    .await;
+
+    match result {
+        Ok(proposal) => (StatusCode::OK, Json(ApiResponse::success(proposal))),
+        Err(e) => {
+            let response = ApiResponse::<MultisigProposal>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn load_proposal(
+    state: &ApiState,
+    id: &str,
+) -> std::result::Result<Transaction, (StatusCode, Json<ApiResponse<MultisigProposal>>)> {
+    match state.storage.load_multisig_proposal(id).await {
+        Ok(Some(transaction)) => Ok(transaction),
+        Ok(None) => {
+            let response = ApiResponse::error("Multisig proposal not found".to_string());
+            Err((StatusCode::NOT_FOUND, Json(response)))
+        }
+        Err(e) => {
+            let response = ApiResponse::error(e.to_string());
+            Err((StatusCode::INTERNAL_SERVER_ERROR, Json(response)))
+        }
+    }
+}
+
+async fn get_multisig_proposal(State(state): State<ApiState>, Path(id): Path<String>) -> impl IntoResponse {
+    let transaction = match load_proposal(&state, &id).await {
+        Ok(transaction) => transaction,
+        Err(response) => return response,
+    };
+
+    match MultisigProposal::from_transaction(transaction) {
+        Ok(proposal) => (StatusCode::OK, Json(ApiResponse::success(proposal))),
+        Err(e) => {
+            let response = ApiResponse::<MultisigProposal>::error(e.to_string());
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
+        }
+    }
+}
+
+async fn sign_multisig_proposal(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(req): Json<PartialSignatureRequest>,
+) -> impl IntoResponse {
+    let mut transaction = match load_proposal(&state, &id).await {
+        Ok(transaction) => transaction,
+        Err(response) => return response,
+    };
+
+    let partial = match (req.public_key, req.signature, req.signer) {
+        (Some(public_key), Some(signature), _) => {
+            PublicKey::from_hex(&public_key).and_then(|public_key| {
+                Ok(PartialSignature {
+                    public_key,
+                    signature: DigitalSignature::from_hex(&signature)?,
+                })
+            })
+        }
+        (_, _, Some(signer)) => {
+            let wallet = state.keyring.write().await.wallet(&signer).cloned();
+            match (wallet, transaction.signable_data()) {
+                (Some(wallet), Ok(data)) => tokio::task::spawn_blocking(move || {
+                    Ok(PartialSignature {
+                        public_key: wallet.public_key().clone(),
+                        signature: wallet.sign_transaction(&data)?,
+                    })
+                })
+                .await
+                .unwrap_or_else(|e| Err(std::io::Error::from(e).into())),
+                (_, Err(e)) => Err(e),
+                (None, Ok(_)) => {
+                    let response = ApiResponse::<MultisigProposal>::error(format!("Wallet {} is locked", signer));
+                    return (StatusCode::LOCKED, Json(response));
+                }
+            }
+        }
+        _ => Err(BlockchainError::InvalidTransaction {
+            message: "Provide either public_key and signature, or an unlocked signer".to_string(),
+        }),
+    };
+
+    let result = partial.and_then(|partial| transaction.add_multisig_signature(partial));
+    if let Err(e) = result {
+        let response = ApiResponse::<MultisigProposal>::error(e.to_string());
+        return (StatusCode::BAD_REQUEST, Json(response));
+    }
+
+    let result = async {
+        state.storage.save_multisig_proposal(&transaction).await?;
+        MultisigProposal::from_transaction(transaction)
+    }
+    .await;
+
+    match result {
+        Ok(proposal) => (StatusCode::OK, Json(ApiResponse::success(proposal))),
+        Err(e) => {
+            let response = ApiResponse::<MultisigProposal>::error(e.to_string());
             (StatusCode::INTERNAL_SERVER_ERROR, Json(response))
         }
     }
 }
 
+async fn submit_multisig_proposal(State(state): State<ApiState>, Path(id): Path<String>) -> impl IntoResponse {
+    let transaction = match state.storage.load_multisig_proposal(&id).await {
+        Ok(Some(transaction)) => transaction,
+        Ok(None) => {
+            let response = ApiResponse::<TransactionSubmission>::error("Multisig proposal not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(response));
+        }
+    };
+
+    let blockchain = state.blockchain.read().await;
+    let mut mempool = state.mempool.write().await;
+
+    let response = submit_transaction(&state, &blockchain, &mut mempool, transaction);
+    if response.0 == StatusCode::OK {
+        if let Err(e) = state.storage.delete_multisig_proposal(&id).await {
+            warn!("Failed to delete submitted multisig proposal {}: {}", id, e);
+        }
+    }
+
+    response
+}
+
 // Smart contract API handlers
 async fn list_contracts(State(state): State<ApiState>) -> impl IntoResponse {
     let engine = state.contract_engine.read().await;
@@ -409,25 +1519,29 @@ async fn deploy_contract(
     State(state): State<ApiState>,
     Json(req): Json<ContractDeployRequest>,
 ) -> impl IntoResponse {
-    let _code = match BASE64_STANDARD.decode(req.code) {
+    let owner = match parse_address(&req.owner) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let code = match BASE64_STANDARD.decode(req.code) {
         Ok(code) => code,
         Err(_) => {
-            let response = ApiResponse::<SmartContract>::error("Invalid base64 code".to_string());
+            let response = ApiResponse::<TransactionSubmission>::error("Invalid base64 code".to_string());
             return (StatusCode::BAD_REQUEST, Json(response));
         }
     };
 
-    // This is simplified - in reality you'd need to parse the ABI
-    let contract = SmartContract::simple_storage_contract();
-
-    let mut engine = state.contract_engine.write().await;
-    match engine.deploy_contract(contract.clone()) {
-        Ok(()) => (StatusCode::OK, Json(ApiResponse::success(contract))),
-        Err(e) => {
-            let response = ApiResponse::<SmartContract>::error(e.to_string());
-            (StatusCode::BAD_REQUEST, Json(response))
-        }
+    // Reject code the engine would refuse before it costs a fee
+    let abi = req.abi.unwrap_or_default();
+    if let Err(e) = state.contract_engine.read().await.check_deployment(&code, abi.clone()) {
+        let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+        return (StatusCode::BAD_REQUEST, Json(response));
     }
+
+    // Deployed by the engine once the transaction is mined; the contract id is `transaction.to`
+    let transaction = Transaction::contract_deploy(owner, req.name, code, abi, req.gas_limit);
+    sign_and_submit(&state, transaction, req.fee, req.nonce, req.private_key.as_deref()).await
 }
 
 async fn get_contract(State(state): State<ApiState>, Path(id): Path<String>) -> impl IntoResponse {
@@ -447,21 +1561,83 @@ async fn call_contract(
     Path(id): Path<String>,
     Json(req): Json<ContractCallRequest>,
 ) -> impl IntoResponse {
-    // This is simplified - in reality you'd need to convert the JSON args to ContractValues
-    let call = ContractCall {
-        contract_id: id,
-        function_name: req.function_name,
-        args: vec![], // Simplified
-        caller: req.caller,
-        value: req.value,
-        gas_limit: req.gas_limit,
-    };
-
-    let mut engine = state.contract_engine.write().await;
-    match engine.call_contract(call) {
+    let caller = match parse_address(&req.caller) {
+        Ok(address) => address,
+        Err(response) => return response,
+    };
+
+    let args = match state.contract_engine.read().await.get_contract(&id) {
+        Some(contract) => contract
+            .callable_function(&req.function_name, req.value)
+            .and_then(|_| contract.parse_args(&req.function_name, &req.args)),
+        None => {
+            let response = ApiResponse::<TransactionSubmission>::error("Contract not found".to_string());
+            return (StatusCode::NOT_FOUND, Json(response));
+        }
+    };
+    let args = match args {
+        Ok(args) => args,
+        Err(e) => {
+            let response = ApiResponse::<TransactionSubmission>::error(e.to_string());
+            return (StatusCode::BAD_REQUEST, Json(response));
+        }
+    };
+
+    let transaction = Transaction::contract_call(caller, id, req.function_name, args, req.value, req.gas_limit);
+    sign_and_submit(&state, transaction, req.fee, req.nonce, req.private_key.as_deref()).await
+}
+
+/// Runs a read-only call right away, without a transaction or fee. The call
+/// runs on a snapshot of the engine on the blocking pool, so a slow view
+/// neither stalls the async runtime nor holds up mining.
+async fn view_contract(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+    Json(req): Json<ContractViewRequest>,
+) -> impl IntoResponse {
+    let caller = match req.caller.as_deref().map(parse_address::<ExecutionResult>).transpose() {
+        Ok(caller) => caller.unwrap_or_default(),
+        Err(response) => return response,
+    };
+
+    let (snapshot, call) = {
+        let engine = state.contract_engine.read().await;
+        let contract = match engine.get_contract(&id) {
+            Some(contract) => contract,
+            None => {
+                let response = ApiResponse::<ExecutionResult>::error("Contract not found".to_string());
+                return (StatusCode::NOT_FOUND, Json(response));
+            }
+        };
+
+        let args = match contract.parse_args(&req.function_name, &req.args) {
+            Ok(args) => args,
+            Err(e) => {
+                let response = ApiResponse::<ExecutionResult>::error(e.to_string());
+                return (StatusCode::BAD_REQUEST, Json(response));
+            }
+        };
+        let call = ContractCall {
+            contract_id: id.clone(),
+            function_name: req.function_name,
+            args,
+            caller,
+            value: 0.0,
+            gas_limit: req
+                .gas_limit
+                .unwrap_or_else(|| engine.config().max_gas_limit.min(contract.gas_limit)),
+        };
+        (engine.snapshot(), call)
+    };
+
+    let result = tokio::task::spawn_blocking(move || snapshot.view(call))
+        .await
+        .unwrap_or_else(|e| Err(std::io::Error::from(e).into()));
+
+    match result {
         Ok(result) => (StatusCode::OK, Json(ApiResponse::success(result))),
         Err(e) => {
-            let response = ApiResponse::<crate::contracts::ExecutionResult>::error(e.to_string());
+            let response = ApiResponse::<ExecutionResult>::error(e.to_string());
             (StatusCode::BAD_REQUEST, Json(response))
         }
     }
@@ -494,8 +1670,35 @@ pub async fn start_server(state: ApiState, port: u16) -> Result<()> {
         .map_err(|e| BlockchainError::Io(e))?;
 
     axum::serve(listener, app)
+        .with_graceful_shutdown(shutdown_signal())
         .await
         .map_err(|e| BlockchainError::Io(std::io::Error::new(std::io::ErrorKind::Other, e)))?;
 
     Ok(())
-}
\ No newline at end of file
+}
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+
+    info!("Shutdown signal received, stopping API server");
+}
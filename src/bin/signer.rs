@@ -0,0 +1,80 @@
+//! Signer daemon: holds one decrypted key and signs on behalf of a node over
+//! a Unix socket, so the key never enters the node's memory.
+
+use anyhow::Result;
+use clap::Parser;
+
+#[derive(Parser)]
+#[command(name = "blockchain-signer")]
+#[command(about = "Serve signing requests for one wallet over a Unix socket")]
+#[command(version = "2.0.0")]
+struct Cli {
+    #[arg(long, help = "Keystore file (from export-wallet or the export API)")]
+    keystore: String,
+    #[arg(long, default_value = "blockchain-signer.sock", help = "Socket path to listen on")]
+    socket: String,
+    #[arg(long, help = "Keystore password (prompted if omitted)")]
+    password: Option<String>,
+}
+
+#[cfg(unix)]
+fn main() -> Result<()> {
+    use blockchain::{signer, EncryptedKey, KeyPair};
+    use std::fs;
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+    use std::os::unix::net::UnixListener;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tracing::{info, Level};
+
+    tracing_subscriber::fmt().with_max_level(Level::INFO).init();
+
+    let cli = Cli::parse();
+    let keystore = EncryptedKey::read_file(&cli.keystore)?;
+    let password = match cli.password {
+        Some(password) => password,
+        None => rpassword::prompt_password("Keystore password: ")?,
+    };
+    let keypair = KeyPair::from_private_key_bytes(&keystore.decrypt(&password)?)?;
+
+    // Replace a socket left behind by a previous run, but never another file
+    let socket = Path::new(&cli.socket);
+    if let Ok(metadata) = fs::symlink_metadata(socket) {
+        if !metadata.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", socket.display());
+        }
+        fs::remove_file(socket)?;
+    }
+
+    // Bind inside a directory only we can enter and restrict the socket
+    // before moving it into place, so other users can never connect
+    let name = socket
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("{} is not a file path", socket.display()))?;
+    let private_dir = socket.with_file_name(format!(".{}.{}", name.to_string_lossy(), std::process::id()));
+    fs::DirBuilder::new().mode(0o700).create(&private_dir)?;
+    let bound = private_dir.join(name);
+    let listener = UnixListener::bind(&bound).and_then(|listener| {
+        fs::set_permissions(&bound, fs::Permissions::from_mode(0o600))?;
+        fs::rename(&bound, socket)?;
+        Ok(listener)
+    });
+    let _ = fs::remove_file(&bound);
+    fs::remove_dir(&private_dir)?;
+    let listener = listener?;
+
+    info!(
+        "Signing for {} on {}",
+        keypair.public_key().to_address(),
+        socket.display()
+    );
+    signer::serve(listener, Arc::new(keypair))?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() -> Result<()> {
+    Cli::parse();
+    anyhow::bail!("blockchain-signer requires Unix domain sockets")
+}
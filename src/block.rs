@@ -2,6 +2,7 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::crypto::{verify_batch, SignatureCheck};
 use crate::errors::{BlockchainError, Result};
 use crate::transaction::Transaction;
 
@@ -117,12 +118,51 @@ impl Block {
             });
         }
 
+        let mut signed = Vec::new();
+
         for transaction in &self.transactions {
             if transaction.from.trim().is_empty() || transaction.to.trim().is_empty() {
                 return Err(BlockchainError::InvalidBlock {
                     message: "Transaction contains empty addresses".to_string(),
                 });
             }
+
+            if transaction.is_multisig() && !transaction.verify_signature() {
+                return Err(BlockchainError::InvalidBlock {
+                    message: format!("Transaction {} lacks the required multisig signatures", transaction.id),
+                });
+            }
+
+            transaction.check_kind()?;
+            transaction.check_locks(self.index, self.timestamp)?;
+
+            if transaction.is_system() || transaction.multisig.is_some() {
+                continue;
+            }
+
+            // Whether the key is authorized for the sender depends on chain
+            // state, so `Blockchain` checks that when the block is appended
+            let (Some(signature), Some(public_key)) = (&transaction.signature, &transaction.from_public_key) else {
+                return Err(BlockchainError::InvalidBlock {
+                    message: format!("Transaction {} is not signed", transaction.id),
+                });
+            };
+            signed.push((transaction, transaction.signable_data()?, signature, public_key));
+        }
+
+        let checks: Vec<SignatureCheck> = signed
+            .iter()
+            .map(|(_, message, signature, public_key)| SignatureCheck {
+                message,
+                signature,
+                public_key,
+            })
+            .collect();
+
+        if let Err(index) = verify_batch(&checks) {
+            return Err(BlockchainError::InvalidBlock {
+                message: format!("Transaction {} has an invalid signature", signed[index].0.id),
+            });
         }
 
         Ok(())
@@ -1,12 +1,47 @@
 use crate::block::Block;
+use crate::contracts::ContractReceipt;
+use crate::crypto::PublicKey;
 use crate::errors::{BlockchainError, Result};
 use crate::poh::PohRecorder;
 use crate::transaction::Transaction;
+use std::collections::{HashMap, HashSet};
+use tracing::info;
 
 #[derive(Debug)]
 pub struct Blockchain {
     chain: Vec<Block>,
     poh_recorder: PohRecorder,
+    /// Accounts whose signing key was rotated, mapped to their current key.
+    authorized_keys: HashMap<String, PublicKey>,
+    /// Contract transactions whose execution failed. Their amount stays with
+    /// the sender; only the fee is charged.
+    reverted_calls: HashSet<String>,
+    /// Net value each contract received from other contracts through nested
+    /// calls, which move value without a transaction of their own.
+    contract_transfers: HashMap<String, f64>,
+}
+
+/// A validated block that is not yet part of the chain.
+#[derive(Debug)]
+pub struct PreparedBlock {
+    block: Block,
+    poh_recorder: PohRecorder,
+    rotations: Vec<(String, PublicKey)>,
+}
+
+impl PreparedBlock {
+    pub fn block(&self) -> &Block {
+        &self.block
+    }
+}
+
+/// What the transactions checked so far in a block change, so that later
+/// transactions in the same block are checked against it.
+#[derive(Default)]
+struct BlockEffects {
+    rotations: Vec<(String, PublicKey)>,
+    next_nonces: HashMap<String, u64>,
+    balance_deltas: HashMap<String, f64>,
 }
 
 impl Blockchain {
@@ -21,10 +56,54 @@ impl Blockchain {
         Ok(Blockchain {
             chain: vec![genesis_block],
             poh_recorder,
+            authorized_keys: HashMap::new(),
+            reverted_calls: HashSet::new(),
+            contract_transfers: HashMap::new(),
         })
     }
 
+    /// Rebuilds the chain from stored `blocks`, genesis first. The blocks are
+    /// validated, and the PoH sequence and rotated account keys are replayed
+    /// from them.
+    pub fn from_blocks(blocks: Vec<Block>) -> Result<Self> {
+        if blocks.first().is_none_or(|genesis| genesis.index != 0) {
+            return Err(BlockchainError::ChainValidation {
+                message: "Stored chain does not start with a genesis block".to_string(),
+            });
+        }
+
+        let mut poh_recorder = PohRecorder::new();
+        let mut authorized_keys = HashMap::new();
+        for block in &blocks {
+            if poh_recorder.record(&block.transaction_data()?) != block.poh_hash {
+                return Err(BlockchainError::ChainValidation {
+                    message: format!("Block {} does not continue the PoH sequence", block.index),
+                });
+            }
+            authorized_keys.extend(authorize_block(&authorized_keys, block)?);
+        }
+
+        let blockchain = Blockchain {
+            chain: blocks,
+            poh_recorder,
+            authorized_keys,
+            reverted_calls: HashSet::new(),
+            contract_transfers: HashMap::new(),
+        };
+        blockchain.is_chain_valid()?;
+
+        Ok(blockchain)
+    }
+
     pub fn add_block(&mut self, transactions: Vec<Transaction>) -> Result<()> {
+        let prepared = self.prepare_block(transactions)?;
+        self.commit_block(prepared);
+        Ok(())
+    }
+
+    /// Builds and validates the next block from `transactions` without
+    /// appending it, so what it causes can be persisted first.
+    pub fn prepare_block(&self, transactions: Vec<Transaction>) -> Result<PreparedBlock> {
         if transactions.is_empty() {
             return Err(BlockchainError::InvalidBlock {
                 message: "Cannot create block with no transactions".to_string(),
@@ -42,25 +121,57 @@ impl Blockchain {
             .collect::<Result<Vec<String>>>()?
             .join(",");
 
-        let poh_hash = self.poh_recorder.record(&transaction_data);
+        // Only advance PoH once the block is committed, so the sequence can be
+        // replayed from the stored blocks
+        let mut poh_recorder = self.poh_recorder.clone();
+        let poh_hash = poh_recorder.record(&transaction_data);
 
-        let new_block = Block::new(
+        let block = Block::new(
             previous_index + 1,
             transactions,
             previous_hash,
             poh_hash,
         );
 
-        new_block.is_valid()?;
-        self.chain.push(new_block);
+        block.is_valid()?;
+        let mut effects = BlockEffects::default();
+        for transaction in &block.transactions {
+            self.apply_in_block(&mut effects, transaction)?;
+        }
 
-        Ok(())
+        Ok(PreparedBlock {
+            block,
+            poh_recorder,
+            rotations: effects.rotations,
+        })
+    }
+
+    /// Appends a block prepared against the current tip.
+    pub fn commit_block(&mut self, prepared: PreparedBlock) {
+        debug_assert_eq!(
+            Some(&prepared.block.previous_hash),
+            self.chain.last().map(|block| &block.hash),
+            "block was prepared against a different tip"
+        );
+
+        self.chain.push(prepared.block);
+        self.poh_recorder = prepared.poh_recorder;
+
+        for (address, public_key) in prepared.rotations {
+            info!("Signing key of {} rotated to {}", address, public_key);
+            self.authorized_keys.insert(address, public_key);
+        }
     }
 
     pub fn get_latest_block(&self) -> Result<&Block> {
         self.chain.last().ok_or(BlockchainError::EmptyBlockchain)
     }
 
+    /// Index the next block will have.
+    pub fn next_index(&self) -> u64 {
+        self.chain.len() as u64
+    }
+
     pub fn get_block(&self, index: u64) -> Option<&Block> {
         self.chain.get(index as usize)
     }
@@ -84,8 +195,15 @@ impl Blockchain {
             });
         }
 
+        let mut authorized_keys = HashMap::new();
+        let mut next_nonces: HashMap<&str, u64> = HashMap::new();
         for (i, block) in self.chain.iter().enumerate() {
             block.is_valid()?;
+            authorized_keys.extend(authorize_block(&authorized_keys, block)?);
+            check_nonces(block, |address| next_nonces.get(address).copied().unwrap_or(0))?;
+            for transaction in block.transactions.iter().filter(|tx| !tx.is_system()) {
+                next_nonces.insert(&transaction.from, transaction.nonce + 1);
+            }
 
             if i > 0 {
                 let previous_block = &self.chain[i - 1];
@@ -116,15 +234,16 @@ impl Blockchain {
     }
 
     pub fn get_balance(&self, address: &str) -> f64 {
-        let mut balance = 0.0;
+        let mut balance = self.contract_transfers.get(address).copied().unwrap_or(0.0);
 
         for block in &self.chain {
             for transaction in &block.transactions {
-                if transaction.to == address {
-                    balance += transaction.amount;
+                let reverted = self.reverted_calls.contains(&transaction.id);
+                if !reverted {
+                    balance += transaction.amount_to(address);
                 }
                 if transaction.from == address && transaction.from != "genesis" {
-                    balance -= transaction.amount;
+                    balance -= if reverted { transaction.fee } else { transaction.amount + transaction.fee };
                 }
             }
         }
@@ -132,9 +251,206 @@ impl Blockchain {
         balance
     }
 
+    pub fn next_nonce(&self, address: &str) -> u64 {
+        self.chain
+            .iter()
+            .flat_map(|block| &block.transactions)
+            .filter(|tx| tx.from == address && !tx.is_coinbase())
+            .map(|tx| tx.nonce + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn poh_tick_count(&self) -> u64 {
         self.poh_recorder.tick_count()
     }
+
+    /// The key `address` rotated to, or `None` if it still signs with the key
+    /// its address was derived from.
+    pub fn authorized_key(&self, address: &str) -> Option<&PublicKey> {
+        self.authorized_keys.get(address)
+    }
+
+    /// Records the outcome of executing a block's contract transactions, so
+    /// the value sent with a failed call is returned to its sender and value
+    /// moved between contracts shows in their balances.
+    pub fn record_contract_receipts(&mut self, receipts: &[ContractReceipt]) {
+        for receipt in receipts {
+            if !receipt.result.success {
+                self.reverted_calls.insert(receipt.transaction_id.clone());
+            }
+            for transfer in &receipt.result.transfers {
+                *self.contract_transfers.entry(transfer.from.clone()).or_default() -= transfer.amount;
+                *self.contract_transfers.entry(transfer.to.clone()).or_default() += transfer.amount;
+            }
+        }
+    }
+
+    /// Picks the candidates, in order, that can go into the next block
+    /// together. A candidate that fails the block's checks is left out along
+    /// with its sender's later candidates, which would follow a nonce gap;
+    /// only the failing one is returned as rejected, with the reason.
+    pub fn select_transactions<'a>(
+        &self,
+        candidates: impl IntoIterator<Item = &'a Transaction>,
+    ) -> (Vec<Transaction>, Vec<(String, BlockchainError)>) {
+        let mut effects = BlockEffects::default();
+        let mut selected = Vec::new();
+        let mut rejected = Vec::new();
+        let mut blocked_senders: HashSet<&str> = HashSet::new();
+
+        for transaction in candidates {
+            if blocked_senders.contains(transaction.from.as_str()) {
+                continue;
+            }
+
+            match self.apply_in_block(&mut effects, transaction) {
+                Ok(()) => selected.push(transaction.clone()),
+                Err(e) => {
+                    blocked_senders.insert(&transaction.from);
+                    rejected.push((transaction.id.clone(), e));
+                }
+            }
+        }
+
+        (selected, rejected)
+    }
+
+    /// Checks `transaction` as the next one in a block after those recorded in
+    /// `effects`, then records its effects. It must be signed by its sender's
+    /// key at that point, continue the sender's nonce sequence, and be paid
+    /// for in full by the sender's balance at that point.
+    fn apply_in_block(&self, effects: &mut BlockEffects, transaction: &Transaction) -> Result<()> {
+        if !transaction.is_system() {
+            let from = transaction.from.as_str();
+
+            if transaction.multisig.is_none() {
+                check_signer(transaction, current_key(&effects.rotations, &self.authorized_keys, from))?;
+            }
+
+            let expected_nonce = match effects.next_nonces.get(from) {
+                Some(nonce) => *nonce,
+                None => self.next_nonce(from),
+            };
+            check_nonce(transaction, expected_nonce)?;
+
+            let available = self.get_balance(from) + effects.balance_deltas.get(from).copied().unwrap_or(0.0);
+            let required = transaction.amount + transaction.fee;
+            if available < required {
+                return Err(BlockchainError::InvalidTransaction {
+                    message: format!(
+                        "Transaction {} needs {} but {} has {}",
+                        transaction.id, required, from, available
+                    ),
+                });
+            }
+
+            effects.next_nonces.insert(from.to_string(), expected_nonce + 1);
+            *effects.balance_deltas.entry(from.to_string()).or_insert(0.0) -= transaction.amount + transaction.fee;
+            if let Some(new_key) = transaction.new_public_key() {
+                effects.rotations.push((from.to_string(), new_key.clone()));
+            }
+        }
+
+        for (to, amount) in transaction.credits() {
+            *effects.balance_deltas.entry(to.to_string()).or_insert(0.0) += amount;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `transaction` is signed by its sender's current key.
+    pub fn check_signer(&self, transaction: &Transaction) -> Result<()> {
+        check_signer(transaction, self.authorized_key(&transaction.from))
+    }
+}
+
+/// Checks every single-key transaction in `block` against the sender's
+/// key at that point, so a rotation takes effect for later transactions in the
+/// same block, and returns the rotations the block applies.
+fn authorize_block(
+    authorized_keys: &HashMap<String, PublicKey>,
+    block: &Block,
+) -> Result<Vec<(String, PublicKey)>> {
+    let mut rotations: Vec<(String, PublicKey)> = Vec::new();
+
+    for transaction in &block.transactions {
+        if transaction.is_system() || transaction.multisig.is_some() {
+            continue;
+        }
+
+        check_signer(transaction, current_key(&rotations, authorized_keys, &transaction.from))?;
+
+        if let Some(new_key) = transaction.new_public_key() {
+            rotations.push((transaction.from.clone(), new_key.clone()));
+        }
+    }
+
+    Ok(rotations)
+}
+
+/// Checks that each sender's transactions in `block` continue its nonce
+/// sequence from `next_nonce` with no gaps or reuse, so at most one
+/// transaction per sender and nonce is ever confirmed.
+fn check_nonces(block: &Block, next_nonce: impl Fn(&str) -> u64) -> Result<()> {
+    let mut expected_nonces: HashMap<&str, u64> = HashMap::new();
+
+    for transaction in block.transactions.iter().filter(|tx| !tx.is_system()) {
+        let expected = expected_nonces
+            .entry(transaction.from.as_str())
+            .or_insert_with(|| next_nonce(&transaction.from));
+        check_nonce(transaction, *expected)?;
+        *expected += 1;
+    }
+
+    Ok(())
+}
+
+fn check_nonce(transaction: &Transaction, expected: u64) -> Result<()> {
+    if transaction.nonce != expected {
+        return Err(BlockchainError::InvalidTransaction {
+            message: format!(
+                "Transaction {} has nonce {}, expected {} for {}",
+                transaction.id, transaction.nonce, expected, transaction.from
+            ),
+        });
+    }
+
+    Ok(())
+}
+
+/// The key `address` signs with after `rotations`, the rotations made
+/// earlier in the same block.
+fn current_key<'a>(
+    rotations: &'a [(String, PublicKey)],
+    authorized_keys: &'a HashMap<String, PublicKey>,
+    address: &str,
+) -> Option<&'a PublicKey> {
+    rotations
+        .iter()
+        .rev()
+        .find(|(rotated, _)| rotated == address)
+        .map(|(_, key)| key)
+        .or_else(|| authorized_keys.get(address))
+}
+
+fn check_signer(transaction: &Transaction, authorized_key: Option<&PublicKey>) -> Result<()> {
+    let authorized = match (&transaction.signature, &transaction.from_public_key, authorized_key) {
+        (Some(_), Some(signer), Some(authorized_key)) => signer == authorized_key,
+        (Some(_), Some(signer), None) => signer.to_address() == transaction.from,
+        _ => false,
+    };
+
+    if !authorized {
+        return Err(BlockchainError::InvalidTransaction {
+            message: format!(
+                "Transaction {} is not signed by the authorized key of {}",
+                transaction.id, transaction.from
+            ),
+        });
+    }
+
+    Ok(())
 }
 
 impl Default for Blockchain {
@@ -2,6 +2,7 @@ use clap::{Parser, Subcommand};
 use colored::*;
 use std::io::{self, Write};
 
+use crate::address::validate_address;
 use crate::blockchain::Blockchain;
 use crate::errors::Result;
 use crate::transaction::Transaction;
@@ -111,12 +112,14 @@ impl InteractiveMode {
 
             let data = if data.trim().is_empty() { None } else { Some(data.trim().to_string()) };
 
-            let transaction = Transaction::new(
-                from.trim().to_string(),
-                to.trim().to_string(),
-                amount,
-                data,
-            )?;
+            // "genesis" stays accepted as a sender so demo chains can be funded
+            let from = match from.trim() {
+                "genesis" => "genesis".to_string(),
+                from => validate_address(from)?,
+            };
+            let to = validate_address(&to)?;
+
+            let transaction = Transaction::new(from, to, amount, data)?;
 
             transactions.push(transaction);
             println!("{}", "✅ Transaction added".green());
@@ -176,11 +179,11 @@ impl InteractiveMode {
     }
 
     fn show_balance_interactive(&self) -> Result<()> {
-        let address = self.get_user_input("Enter address to check: ")?;
-        let balance = self.blockchain.get_balance(address.trim());
+        let address = validate_address(&self.get_user_input("Enter address to check: ")?)?;
+        let balance = self.blockchain.get_balance(&address);
 
         println!("{}", format!("💰 Balance for {}: {}",
-            address.trim().bright_cyan(),
+            address.bright_cyan(),
             balance.to_string().bright_yellow().bold()
         ));
 
@@ -0,0 +1,110 @@
+use crate::api::{AddressMempoolView, ApiResponse, TransactionSubmission};
+use crate::errors::{BlockchainError, Result};
+use crate::transaction::Transaction;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Bytes;
+use hyper::{Method, Request, Uri};
+use hyper_util::rt::TokioIo;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// Minimal HTTP client for a node's REST API, used by CLI commands that talk
+/// to a running node (e.g. broadcasting an offline-signed transaction).
+pub struct NodeClient {
+    host: String,
+    port: u16,
+}
+
+impl NodeClient {
+    /// Accepts a base URL such as `http://127.0.0.1:8080`. Only plain HTTP is supported.
+    pub fn new(url: &str) -> Result<Self> {
+        let uri: Uri = url.parse().map_err(|e| client_error(format!("Invalid node URL '{}': {}", url, e)))?;
+
+        if uri.scheme_str().is_some_and(|scheme| scheme != "http") {
+            return Err(client_error(format!("Unsupported node URL scheme in '{}', use http://", url)));
+        }
+
+        let host = uri
+            .host()
+            .ok_or_else(|| client_error(format!("Node URL '{}' has no host", url)))?
+            .to_string();
+
+        Ok(NodeClient {
+            host,
+            port: uri.port_u16().unwrap_or(80),
+        })
+    }
+
+    /// Next nonce for `address`, counting transactions still pending in the node's mempool.
+    pub async fn next_nonce(&self, address: &str) -> Result<u64> {
+        let view: AddressMempoolView = self
+            .request(Method::GET, &format!("/api/mempool/{}", address), None::<&()>)
+            .await?;
+        Ok(view.next_nonce)
+    }
+
+    pub async fn submit_signed_transaction(&self, transaction: &Transaction) -> Result<TransactionSubmission> {
+        self.request(Method::POST, "/api/transactions/signed", Some(transaction))
+            .await
+    }
+
+    async fn request<T: DeserializeOwned, B: Serialize>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<T> {
+        debug!("{} http://{}:{}{}", method, self.host, self.port, path);
+
+        let stream = TcpStream::connect((self.host.as_str(), self.port))
+            .await
+            .map_err(|e| client_error(format!("Failed to connect to {}:{}: {}", self.host, self.port, e)))?;
+
+        let (mut sender, connection) = hyper::client::conn::http1::handshake(TokioIo::new(stream))
+            .await
+            .map_err(|e| client_error(format!("HTTP handshake failed: {}", e)))?;
+        tokio::spawn(connection);
+
+        let body = match body {
+            Some(body) => Bytes::from(serde_json::to_vec(body)?),
+            None => Bytes::new(),
+        };
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(hyper::header::HOST, format!("{}:{}", self.host, self.port))
+            .header(hyper::header::CONTENT_TYPE, "application/json")
+            .body(Full::new(body))
+            .map_err(|e| client_error(format!("Invalid request: {}", e)))?;
+
+        let response = sender
+            .send_request(request)
+            .await
+            .map_err(|e| client_error(format!("Request failed: {}", e)))?;
+        let status = response.status();
+        let bytes = response
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| client_error(format!("Failed to read response: {}", e)))?
+            .to_bytes();
+
+        let response: ApiResponse<T> = serde_json::from_slice(&bytes).map_err(|_| {
+            client_error(format!("Node returned {}: {}", status, String::from_utf8_lossy(&bytes)))
+        })?;
+        match response.data {
+            Some(data) if response.success => Ok(data),
+            _ => Err(client_error(format!(
+                "Node returned {}: {}",
+                status,
+                response.error.unwrap_or_else(|| "no details".to_string())
+            ))),
+        }
+    }
+}
+
+fn client_error(message: String) -> BlockchainError {
+    BlockchainError::Io(std::io::Error::other(message))
+}
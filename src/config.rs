@@ -3,12 +3,14 @@ use serde::{Deserialize, Serialize};
 use std::path::Path;
 use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct BlockchainConfig {
     pub node: NodeConfig,
     pub database: DatabaseConfig,
     pub mining: MiningConfig,
     pub network: NetworkConfig,
+    pub mempool: MempoolConfig,
+    pub keystore: KeystoreConfig,
     pub api: ApiConfig,
     pub contracts: ContractsConfig,
     pub logging: LoggingConfig,
@@ -49,6 +51,19 @@ pub struct NetworkConfig {
     pub enabled: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MempoolConfig {
+    pub max_transactions: usize,
+    pub min_fee_bump_percent: f64,
+    pub persist_interval_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeystoreConfig {
+    pub unlock_timeout_secs: u64,
+    pub max_unlock_timeout_secs: u64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiConfig {
     pub enabled: bool,
@@ -65,8 +80,27 @@ pub struct ContractsConfig {
     pub enabled: bool,
     pub max_memory_mb: usize,
     pub execution_timeout_secs: u64,
+    /// Timeout for read-only view calls, which anyone can make without a fee.
+    pub view_timeout_secs: u64,
     pub max_gas_limit: u64,
     pub gas_price: f64,
+    /// Maximum number of nested contract-to-contract calls below a top-level call.
+    pub max_call_depth: usize,
+    /// Whether a contract may be called again while it is already executing.
+    pub allow_reentrancy: bool,
+    pub float_ops: FloatOps,
+}
+
+/// How contracts may use floating point, whose NaN bit patterns can differ
+/// between hosts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FloatOps {
+    /// Allowed, with NaN results canonicalized so every node computes the same bits.
+    #[default]
+    Canonicalize,
+    /// Contracts using floating-point types or instructions are rejected at deployment.
+    Reject,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,20 +118,6 @@ pub enum LogOutput {
     Both { path: String },
 }
 
-impl Default for BlockchainConfig {
-    fn default() -> Self {
-        Self {
-            node: NodeConfig::default(),
-            database: DatabaseConfig::default(),
-            mining: MiningConfig::default(),
-            network: NetworkConfig::default(),
-            api: ApiConfig::default(),
-            contracts: ContractsConfig::default(),
-            logging: LoggingConfig::default(),
-        }
-    }
-}
-
 impl Default for NodeConfig {
     fn default() -> Self {
         Self {
@@ -145,6 +165,25 @@ impl Default for NetworkConfig {
     }
 }
 
+impl Default for MempoolConfig {
+    fn default() -> Self {
+        Self {
+            max_transactions: 10_000,
+            min_fee_bump_percent: 10.0,
+            persist_interval_secs: 60,
+        }
+    }
+}
+
+impl Default for KeystoreConfig {
+    fn default() -> Self {
+        Self {
+            unlock_timeout_secs: 300, // 5 minutes
+            max_unlock_timeout_secs: 3600, // 1 hour
+        }
+    }
+}
+
 impl Default for ApiConfig {
     fn default() -> Self {
         Self {
@@ -165,8 +204,12 @@ impl Default for ContractsConfig {
             enabled: true,
             max_memory_mb: 16,
             execution_timeout_secs: 30,
+            view_timeout_secs: 2,
             max_gas_limit: 1_000_000,
             gas_price: 0.001,
+            max_call_depth: 8,
+            allow_reentrancy: false,
+            float_ops: FloatOps::Canonicalize,
         }
     }
 }
@@ -241,6 +284,32 @@ impl BlockchainConfig {
             });
         }
 
+        // Validate mempool configuration
+        if self.mempool.max_transactions == 0 {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Mempool max transactions cannot be 0".to_string(),
+            });
+        }
+
+        if self.mempool.min_fee_bump_percent < 0.0 {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Mempool fee bump cannot be negative".to_string(),
+            });
+        }
+
+        if self.mempool.persist_interval_secs == 0 {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Mempool persist interval cannot be 0".to_string(),
+            });
+        }
+
+        // Validate keystore configuration
+        if self.keystore.unlock_timeout_secs > self.keystore.max_unlock_timeout_secs {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Wallet unlock timeout cannot exceed the maximum unlock timeout".to_string(),
+            });
+        }
+
         // Validate contracts configuration
         if self.contracts.max_memory_mb == 0 {
             return Err(BlockchainError::InvalidBlock {
@@ -248,6 +317,18 @@ impl BlockchainConfig {
             });
         }
 
+        if self.contracts.execution_timeout_secs == 0 {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Contract execution timeout cannot be 0".to_string(),
+            });
+        }
+
+        if self.contracts.view_timeout_secs == 0 {
+            return Err(BlockchainError::InvalidBlock {
+                message: "Contract view timeout cannot be 0".to_string(),
+            });
+        }
+
         // Validate data directory
         if self.node.data_dir.is_empty() {
             return Err(BlockchainError::InvalidBlock {
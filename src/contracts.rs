@@ -1,10 +1,58 @@
+use crate::address::validate_address;
+use crate::block::Block;
+use crate::config::{ContractsConfig, FloatOps};
 use crate::errors::{BlockchainError, Result};
+use crate::transaction::{Transaction, TransactionKind};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::time::{Duration, Instant};
-use wasmtime::{Config, Engine, Module};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::Duration;
+use wasmtime::{
+    AsContextMut, Caller, Config, Engine, Extern, ExternType, Instance, Linker, Memory, Module, ResourceLimiter, Store, Trap,
+    Val, ValType,
+};
 use tracing::{debug, info, warn};
 
+/// Import module providing the host functions linked into every contract
+/// instance. See docs/CONTRACTS.md for the ABI.
+pub const HOST_MODULE: &str = "nchain";
+
+/// Functions of `HOST_MODULE`, the only imports a contract may declare.
+pub const HOST_FUNCTIONS: &[&str] = &[
+    "storage_get",
+    "storage_set",
+    "storage_delete",
+    "caller",
+    "self_address",
+    "value",
+    "balance",
+    "block_index",
+    "block_timestamp",
+    "emit_event",
+    "log",
+    "call",
+];
+
+/// Export a contract provides to receive string, bytes and address arguments:
+/// `alloc(len: i32) -> i32` returns a pointer to `len` writable bytes.
+pub const ALLOC_EXPORT: &str = "alloc";
+
+/// Custom section a module can embed its JSON-encoded `ContractABI` in.
+pub const ABI_SECTION: &str = "abi";
+
+/// Gas charged for every host call.
+pub const HOST_CALL_GAS: u64 = 100;
+/// Extra gas for every byte copied between the host and guest memory.
+pub const HOST_BYTE_GAS: u64 = 1;
+/// Extra gas for a storage write or delete.
+pub const STORAGE_WRITE_GAS: u64 = 5_000;
+
+/// Interval at which the WASM engine's epoch advances. Execution timeouts
+/// are rounded up to whole ticks.
+pub const EPOCH_TICK: Duration = Duration::from_millis(10);
+/// Maximum number of elements in a contract's table.
+pub const MAX_TABLE_ELEMENTS: usize = 10_000;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SmartContract {
     pub id: String,
@@ -16,13 +64,13 @@ pub struct SmartContract {
     pub gas_limit: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ContractABI {
     pub functions: Vec<FunctionSignature>,
     pub events: Vec<EventSignature>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct FunctionSignature {
     pub name: String,
     pub inputs: Vec<ParamType>,
@@ -31,13 +79,64 @@ pub struct FunctionSignature {
     pub gas_cost: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl ContractABI {
+    pub fn function(&self, name: &str) -> Option<&FunctionSignature> {
+        self.functions.iter().find(|function| function.name == name)
+    }
+}
+
+impl FunctionSignature {
+    /// Converts JSON arguments to values of the declared input types.
+    pub fn parse_args(&self, args: &[serde_json::Value]) -> Result<Vec<ContractValue>> {
+        self.check_arity(args.len())?;
+        self.inputs
+            .iter()
+            .zip(args)
+            .enumerate()
+            .map(|(index, (param, arg))| {
+                param.value_from_json(arg).map_err(|reason| BlockchainError::InvalidTransaction {
+                    message: format!("Argument {} of '{}': {}", index + 1, self.name, reason),
+                })
+            })
+            .collect()
+    }
+
+    /// Checks that `args` have the declared input types.
+    pub fn check_args(&self, args: &[ContractValue]) -> Result<()> {
+        self.check_arity(args.len())?;
+        for (index, (param, arg)) in self.inputs.iter().zip(args).enumerate() {
+            if arg.param_type() != *param {
+                return Err(BlockchainError::InvalidTransaction {
+                    message: format!(
+                        "Argument {} of '{}' is {:?} but the ABI expects {:?}",
+                        index + 1,
+                        self.name,
+                        arg.param_type(),
+                        param
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn check_arity(&self, count: usize) -> Result<()> {
+        if count != self.inputs.len() {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("'{}' takes {} arguments, got {}", self.name, self.inputs.len(), count),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct EventSignature {
     pub name: String,
     pub inputs: Vec<ParamType>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum ParamType {
     U32,
     U64,
@@ -50,6 +149,76 @@ pub enum ParamType {
     Address,
 }
 
+impl ParamType {
+    /// Converts a JSON value to this type. 64-bit integers may also be given
+    /// as decimal strings, since JSON numbers lose precision above 2^53, and
+    /// bytes as a hex string.
+    pub fn value_from_json(&self, value: &serde_json::Value) -> std::result::Result<ContractValue, String> {
+        use serde_json::Value;
+
+        let mismatch = || format!("expected {:?}, got {}", self, value);
+        let integer = |value: &Value| match value {
+            Value::Number(number) => number.as_i128(),
+            Value::String(s) => s.trim().parse::<i128>().ok(),
+            _ => None,
+        };
+        let out_of_range = |n: i128| format!("{} is out of range for {:?}", n, self);
+
+        Ok(match self {
+            ParamType::U32 => {
+                let n = integer(value).ok_or_else(mismatch)?;
+                ContractValue::U32(u32::try_from(n).map_err(|_| out_of_range(n))?)
+            }
+            ParamType::U64 => {
+                let n = integer(value).ok_or_else(mismatch)?;
+                ContractValue::U64(u64::try_from(n).map_err(|_| out_of_range(n))?)
+            }
+            ParamType::I32 => {
+                let n = integer(value).ok_or_else(mismatch)?;
+                ContractValue::I32(i32::try_from(n).map_err(|_| out_of_range(n))?)
+            }
+            ParamType::I64 => {
+                let n = integer(value).ok_or_else(mismatch)?;
+                ContractValue::I64(i64::try_from(n).map_err(|_| out_of_range(n))?)
+            }
+            ParamType::F32 => ContractValue::F32(value.as_f64().ok_or_else(mismatch)? as f32),
+            ParamType::F64 => ContractValue::F64(value.as_f64().ok_or_else(mismatch)?),
+            ParamType::String => ContractValue::String(value.as_str().ok_or_else(mismatch)?.to_string()),
+            ParamType::Bytes => {
+                let hex_string = value.as_str().ok_or_else(mismatch)?;
+                let bytes = hex::decode(hex_string.trim_start_matches("0x"))
+                    .map_err(|e| format!("expected hex-encoded Bytes: {}", e))?;
+                ContractValue::Bytes(bytes)
+            }
+            ParamType::Address => {
+                let address = value.as_str().ok_or_else(mismatch)?;
+                ContractValue::Address(validate_address(address).map_err(|e| e.to_string())?)
+            }
+        })
+    }
+
+    /// WASM parameters this is passed as. Strings, bytes and addresses are
+    /// passed as a pointer and length into guest memory.
+    pub fn wasm_params(&self) -> Vec<ValType> {
+        match self {
+            ParamType::String | ParamType::Bytes | ParamType::Address => vec![ValType::I32, ValType::I32],
+            other => vec![other.wasm_result()],
+        }
+    }
+
+    /// WASM result this is returned as. Strings, bytes and addresses are
+    /// returned as `(ptr << 32) | len` in an `i64`.
+    pub fn wasm_result(&self) -> ValType {
+        match self {
+            ParamType::U32 | ParamType::I32 => ValType::I32,
+            ParamType::U64 | ParamType::I64 => ValType::I64,
+            ParamType::F32 => ValType::F32,
+            ParamType::F64 => ValType::F64,
+            ParamType::String | ParamType::Bytes | ParamType::Address => ValType::I64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContractCall {
     pub contract_id: String,
@@ -60,7 +229,7 @@ pub struct ContractCall {
     pub gas_limit: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ContractValue {
     U32(u32),
     U64(u64),
@@ -73,6 +242,22 @@ pub enum ContractValue {
     Address(String),
 }
 
+impl ContractValue {
+    pub fn param_type(&self) -> ParamType {
+        match self {
+            ContractValue::U32(_) => ParamType::U32,
+            ContractValue::U64(_) => ParamType::U64,
+            ContractValue::I32(_) => ParamType::I32,
+            ContractValue::I64(_) => ParamType::I64,
+            ContractValue::F32(_) => ParamType::F32,
+            ContractValue::F64(_) => ParamType::F64,
+            ContractValue::String(_) => ParamType::String,
+            ContractValue::Bytes(_) => ParamType::Bytes,
+            ContractValue::Address(_) => ParamType::Address,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
     pub success: bool,
@@ -81,6 +266,25 @@ pub struct ExecutionResult {
     pub logs: Vec<String>,
     pub events: Vec<ContractEvent>,
     pub error: Option<String>,
+    /// Value contracts sent each other through nested calls that kept
+    /// their effects.
+    #[serde(default)]
+    pub transfers: Vec<ValueTransfer>,
+}
+
+/// Value moved from one contract to another by a nested call.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValueTransfer {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+}
+
+/// Outcome of a contract transaction in a confirmed block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContractReceipt {
+    pub transaction_id: String,
+    pub result: ExecutionResult,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,47 +299,255 @@ pub struct ContractState {
     pub balance: f64,
 }
 
+/// Contract writes not yet persisted: deployed contracts, storage keys set
+/// (`Some`) or deleted (`None`) per contract, and updated balances.
+#[derive(Debug, Clone, Default)]
+pub struct ContractChanges {
+    pub deployed: Vec<SmartContract>,
+    pub storage: BTreeMap<String, BTreeMap<String, Option<ContractValue>>>,
+    pub balances: BTreeMap<String, f64>,
+}
+
+impl ContractChanges {
+    pub fn is_empty(&self) -> bool {
+        self.deployed.is_empty() && self.storage.is_empty() && self.balances.is_empty()
+    }
+}
+
+/// Block a contract call executes in, as seen through the host functions.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct BlockContext {
+    pub index: u64,
+    /// Unix timestamp in seconds.
+    pub timestamp: i64,
+}
+
+impl From<&Block> for BlockContext {
+    fn from(block: &Block) -> Self {
+        BlockContext {
+            index: block.index,
+            timestamp: block.timestamp.timestamp(),
+        }
+    }
+}
+
+/// A change recorded with the value it replaced, so `revert_changes` or a
+/// journal checkpoint can undo it.
+#[derive(Debug)]
+enum Undo {
+    Storage {
+        contract_id: String,
+        key: String,
+        previous: Option<ContractValue>,
+    },
+    Balance {
+        contract_id: String,
+        previous: f64,
+    },
+    Deploy {
+        contract_id: String,
+    },
+}
+
+/// Uncommitted writes of one transaction, layered over the committed
+/// contract state. Reverting to a checkpoint undoes the writes made since,
+/// so a failed nested call leaves its caller's writes in place.
+#[derive(Debug, Default)]
+struct Journal {
+    storage: BTreeMap<String, BTreeMap<String, Option<ContractValue>>>,
+    balances: BTreeMap<String, f64>,
+    undo: Vec<Undo>,
+}
+
+impl Journal {
+    fn get<'a>(&'a self, base: &'a HashMap<String, ContractState>, contract_id: &str, key: &str) -> Option<&'a ContractValue> {
+        match self.storage.get(contract_id).and_then(|writes| writes.get(key)) {
+            Some(value) => value.as_ref(),
+            None => base.get(contract_id).and_then(|state| state.storage.get(key)),
+        }
+    }
+
+    /// Sets `key` to `value`, or deletes it if `value` is `None`. Returns
+    /// whether the key was set before.
+    fn set(&mut self, base: &HashMap<String, ContractState>, contract_id: &str, key: String, value: Option<ContractValue>) -> bool {
+        let previous = self.get(base, contract_id, &key).cloned();
+        let existed = previous.is_some();
+        self.storage.entry(contract_id.to_string()).or_default().insert(key.clone(), value);
+        self.undo.push(Undo::Storage {
+            contract_id: contract_id.to_string(),
+            key,
+            previous,
+        });
+        existed
+    }
+
+    fn balance(&self, base: &HashMap<String, ContractState>, contract_id: &str) -> f64 {
+        match self.balances.get(contract_id) {
+            Some(balance) => *balance,
+            None => base.get(contract_id).map_or(0.0, |state| state.balance),
+        }
+    }
+
+    fn adjust_balance(&mut self, base: &HashMap<String, ContractState>, contract_id: &str, amount: f64) {
+        let previous = self.balance(base, contract_id);
+        self.balances.insert(contract_id.to_string(), previous + amount);
+        self.undo.push(Undo::Balance {
+            contract_id: contract_id.to_string(),
+            previous,
+        });
+    }
+
+    fn checkpoint(&self) -> usize {
+        self.undo.len()
+    }
+
+    fn revert_to(&mut self, checkpoint: usize) {
+        while self.undo.len() > checkpoint {
+            match self.undo.pop() {
+                Some(Undo::Storage { contract_id, key, previous }) => {
+                    self.storage.entry(contract_id).or_default().insert(key, previous);
+                }
+                Some(Undo::Balance { contract_id, previous }) => {
+                    self.balances.insert(contract_id, previous);
+                }
+                Some(Undo::Deploy { .. }) | None => {}
+            }
+        }
+    }
+}
+
+/// Caps the linear memory of all instances in a call's store, and the size of
+/// each table. Each nested call instantiates its callee in the same store,
+/// which keeps the instance until the outermost call ends, so the cap is
+/// cumulative: memory of returned frames stays counted.
+struct StoreLimiter {
+    max_memory: usize,
+    memory: usize,
+}
+
+impl ResourceLimiter for StoreLimiter {
+    fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        let total = self.memory.saturating_sub(current).saturating_add(desired);
+        if total > self.max_memory {
+            return Ok(false);
+        }
+        self.memory = total;
+        Ok(true)
+    }
+
+    fn table_growing(&mut self, _current: usize, desired: usize, _maximum: Option<usize>) -> wasmtime::Result<bool> {
+        Ok(desired <= MAX_TABLE_ELEMENTS)
+    }
+}
+
+/// Data available to host functions during one call. Writes go to the
+/// journal and are committed only if the call succeeds. `contract_id`,
+/// `caller` and `value` describe the innermost frame of `call_stack`.
+struct HostState {
+    contract_id: String,
+    caller: String,
+    value: f64,
+    block: BlockContext,
+    base: Arc<HashMap<String, ContractState>>,
+    journal: Journal,
+    logs: Vec<String>,
+    events: Vec<ContractEvent>,
+    transfers: Vec<ValueTransfer>,
+    call_stack: Vec<String>,
+    linker: Arc<Linker<HostState>>,
+    contracts: Arc<HashMap<String, SmartContract>>,
+    modules: Arc<HashMap<String, Module>>,
+    max_call_depth: usize,
+    allow_reentrancy: bool,
+    limiter: StoreLimiter,
+}
+
 pub struct ContractEngine {
     engine: Engine,
-    contracts: HashMap<String, SmartContract>,
-    contract_states: HashMap<String, ContractState>,
+    linker: Arc<Linker<HostState>>,
+    contracts: Arc<HashMap<String, SmartContract>>,
+    modules: Arc<HashMap<String, Module>>,
+    block: BlockContext,
+    contract_states: Arc<HashMap<String, ContractState>>,
+    changes: ContractChanges,
+    /// Undoes the changes applied since the last `take_changes`.
+    undo: Vec<Undo>,
+    config: ContractsConfig,
     execution_timeout: Duration,
+    view_timeout: Duration,
     max_memory: usize,
 }
 
 impl ContractEngine {
     pub fn new() -> Result<Self> {
+        Self::with_config(ContractsConfig::default())
+    }
+
+    pub fn with_config(contracts_config: ContractsConfig) -> Result<Self> {
         let mut config = Config::new();
         config.wasm_component_model(false);
         config.async_support(false);
         config.consume_fuel(true);
+        config.epoch_interruption(true);
+        // Features whose results can differ between hosts
+        config.wasm_threads(false);
+        config.wasm_relaxed_simd(false);
+        config.cranelift_nan_canonicalization(contracts_config.float_ops == FloatOps::Canonicalize);
 
         let engine = Engine::new(&config).map_err(|e| BlockchainError::InvalidBlock {
             message: format!("Failed to create WASM engine: {}", e),
         })?;
+        spawn_epoch_ticker(&engine);
+
+        let linker = host_linker(&engine).map_err(|e| BlockchainError::InvalidBlock {
+            message: format!("Failed to link host functions: {}", e),
+        })?;
 
         Ok(ContractEngine {
             engine,
-            contracts: HashMap::new(),
-            contract_states: HashMap::new(),
-            execution_timeout: Duration::from_secs(30),
-            max_memory: 16 * 1024 * 1024, // 16MB
+            linker: Arc::new(linker),
+            contracts: Arc::new(HashMap::new()),
+            modules: Arc::new(HashMap::new()),
+            block: BlockContext::default(),
+            changes: ContractChanges::default(),
+            undo: Vec::new(),
+            contract_states: Arc::new(HashMap::new()),
+            execution_timeout: Duration::from_secs(contracts_config.execution_timeout_secs),
+            view_timeout: Duration::from_secs(contracts_config.view_timeout_secs),
+            max_memory: contracts_config.max_memory_mb * 1024 * 1024,
+            config: contracts_config,
         })
     }
 
-    pub fn deploy_contract(&mut self, contract: SmartContract) -> Result<()> {
+    /// Deploys `contract`, taking its ABI from the module's `abi` custom
+    /// section if `contract.abi` is empty. Its gas limit may not exceed
+    /// `ContractsConfig::max_gas_limit`.
+    pub fn deploy_contract(&mut self, mut contract: SmartContract) -> Result<()> {
         info!("Deploying contract: {} ({})", contract.name, contract.id);
 
+        if self.contracts.contains_key(&contract.id) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Contract {} is already deployed", contract.id),
+            });
+        }
+
+        if contract.gas_limit > self.config.max_gas_limit {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!(
+                    "Contract gas limit {} exceeds the maximum of {}",
+                    contract.gas_limit, self.config.max_gas_limit
+                ),
+            });
+        }
+
         // Validate contract before deployment
         self.validate_contract(&contract)?;
 
-        Module::from_binary(&self.engine, &contract.code).map_err(|e| {
-            BlockchainError::InvalidBlock {
-                message: format!("Invalid WASM bytecode: {}", e),
-            }
-        })?;
+        let (module, abi) = self.compile(&contract.code, std::mem::take(&mut contract.abi))?;
+        contract.abi = abi;
+        Arc::make_mut(&mut self.modules).insert(contract.id.clone(), module);
 
-        self.contract_states.insert(
+        Arc::make_mut(&mut self.contract_states).insert(
             contract.id.clone(),
             ContractState {
                 storage: HashMap::new(),
@@ -143,72 +555,398 @@ impl ContractEngine {
             },
         );
 
-        self.contracts.insert(contract.id.clone(), contract);
+        self.undo.push(Undo::Deploy {
+            contract_id: contract.id.clone(),
+        });
+        self.changes.deployed.push(contract.clone());
+        Arc::make_mut(&mut self.contracts).insert(contract.id.clone(), contract);
 
         info!("Contract deployed successfully");
         Ok(())
     }
 
-    pub fn call_contract(&mut self, call: ContractCall) -> Result<ExecutionResult> {
-        let start_time = Instant::now();
-        debug!("Simulating contract call: {:?}", call);
+    /// A copy of the engine sharing this one's contracts, state and block
+    /// context, so views can run without holding a lock on the engine.
+    /// Changes made through the copy are not seen by this engine.
+    pub fn snapshot(&self) -> Self {
+        ContractEngine {
+            engine: self.engine.clone(),
+            linker: Arc::clone(&self.linker),
+            contracts: Arc::clone(&self.contracts),
+            modules: Arc::clone(&self.modules),
+            block: self.block,
+            contract_states: Arc::clone(&self.contract_states),
+            changes: ContractChanges::default(),
+            undo: Vec::new(),
+            config: self.config.clone(),
+            execution_timeout: self.execution_timeout,
+            view_timeout: self.view_timeout,
+            max_memory: self.max_memory,
+        }
+    }
 
-        let contract = self.contracts.get(&call.contract_id).ok_or_else(|| {
-            BlockchainError::InvalidTransaction {
-                message: format!("Contract not found: {}", call.contract_id),
+    /// Loads a contract persisted by an earlier run, with its state. Unlike
+    /// `deploy_contract`, this is not recorded as a change.
+    pub fn restore_contract(&mut self, contract: SmartContract, state: ContractState) -> Result<()> {
+        let module = Module::from_binary(&self.engine, &contract.code).map_err(|e| BlockchainError::InvalidBlock {
+            message: format!("Invalid WASM bytecode for contract {}: {}", contract.id, e),
+        })?;
+
+        Arc::make_mut(&mut self.modules).insert(contract.id.clone(), module);
+        Arc::make_mut(&mut self.contract_states).insert(contract.id.clone(), state);
+        Arc::make_mut(&mut self.contracts).insert(contract.id.clone(), contract);
+        Ok(())
+    }
+
+    /// Changes made since the last `take_changes`, to be persisted with the
+    /// block that caused them.
+    pub fn changes(&self) -> &ContractChanges {
+        &self.changes
+    }
+
+    /// Commits the changes made since the last call, once they have been
+    /// persisted, and returns them.
+    pub fn take_changes(&mut self) -> ContractChanges {
+        self.undo.clear();
+        std::mem::take(&mut self.changes)
+    }
+
+    /// Discards the changes made since the last `take_changes`, restoring
+    /// contract state and removing contracts deployed since.
+    pub fn revert_changes(&mut self) {
+        let states = Arc::make_mut(&mut self.contract_states);
+        while let Some(undo) = self.undo.pop() {
+            match undo {
+                Undo::Storage { contract_id, key, previous } => {
+                    if let Some(state) = states.get_mut(&contract_id) {
+                        match previous {
+                            Some(value) => state.storage.insert(key, value),
+                            None => state.storage.remove(&key),
+                        };
+                    }
+                }
+                Undo::Balance { contract_id, previous } => {
+                    if let Some(state) = states.get_mut(&contract_id) {
+                        state.balance = previous;
+                    }
+                }
+                Undo::Deploy { contract_id } => {
+                    states.remove(&contract_id);
+                    Arc::make_mut(&mut self.modules).remove(&contract_id);
+                    Arc::make_mut(&mut self.contracts).remove(&contract_id);
+                }
             }
+        }
+        self.changes = ContractChanges::default();
+    }
+
+    /// Compiles `code` and checks it against `abi`, or against the ABI in its
+    /// `abi` custom section if `abi` is empty. Returns the ABI that applies.
+    pub fn check_deployment(&self, code: &[u8], abi: ContractABI) -> Result<ContractABI> {
+        self.validate_code(code)?;
+        self.compile(code, abi).map(|(_, abi)| abi)
+    }
+
+    fn compile(&self, code: &[u8], abi: ContractABI) -> Result<(Module, ContractABI)> {
+        let module = Module::from_binary(&self.engine, code).map_err(|e| BlockchainError::InvalidBlock {
+            message: format!("Invalid WASM bytecode: {}", e),
         })?;
 
+        let abi = if abi == ContractABI::default() {
+            embedded_abi(code)?.unwrap_or_default()
+        } else {
+            abi
+        };
+        check_exports(&module, &abi)?;
+
+        Ok((module, abi))
+    }
+
+    /// Runs the exported function `call.function_name` on a fresh instance of
+    /// the contract, metered with one unit of WASM fuel per unit of gas and
+    /// limited to `execution_timeout_secs` and `max_memory_mb`. Traps and
+    /// running out of gas or time are reported in the result's `error`; only
+    /// an unknown contract or a gas limit above the contract's or
+    /// `max_gas_limit` is an `Err`.
+    ///
+    /// A failed call is reverted: its storage writes are discarded and
+    /// `call.value` is not credited to the contract.
+    pub fn call_contract(&mut self, call: ContractCall) -> Result<ExecutionResult> {
+        debug!("Calling contract: {:?}", call);
+
+        let (result, journal) = self.run(&call, self.execution_timeout)?;
+        if let Some(journal) = journal {
+            self.commit(journal);
+        }
+
+        Ok(result)
+    }
+
+    /// Applies a successful call's journal to the contract state, recording
+    /// the writes as changes and their previous values for `revert_changes`.
+    fn commit(&mut self, journal: Journal) {
+        let states = Arc::make_mut(&mut self.contract_states);
+
+        for (contract_id, writes) in journal.storage {
+            let Some(state) = states.get_mut(&contract_id) else {
+                continue;
+            };
+            for (key, value) in &writes {
+                let previous = match value {
+                    Some(value) => state.storage.insert(key.clone(), value.clone()),
+                    None => state.storage.remove(key),
+                };
+                self.undo.push(Undo::Storage {
+                    contract_id: contract_id.clone(),
+                    key: key.clone(),
+                    previous,
+                });
+            }
+            self.changes.storage.entry(contract_id).or_default().extend(writes);
+        }
+
+        for (contract_id, balance) in journal.balances {
+            let Some(state) = states.get_mut(&contract_id) else {
+                continue;
+            };
+            self.undo.push(Undo::Balance {
+                contract_id: contract_id.clone(),
+                previous: state.balance,
+            });
+            state.balance = balance;
+            self.changes.balances.insert(contract_id, balance);
+        }
+    }
+
+    /// Runs a read-only call against a snapshot of the contract's state.
+    /// Storage writes are discarded and nothing is recorded, so views need no
+    /// transaction. Gas is capped at `ContractsConfig::max_gas_limit` and
+    /// time at `ContractsConfig::view_timeout_secs`.
+    pub fn view(&self, call: ContractCall) -> Result<ExecutionResult> {
+        debug!("Viewing contract: {:?}", call);
+
+        if call.value != 0.0 {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "View calls cannot transfer value".to_string(),
+            });
+        }
+
+        self.run(&call, self.view_timeout).map(|(result, _)| result)
+    }
+
+    /// Executes `call` without touching engine state, trapping after
+    /// `timeout`. Returns the journal of the call's writes if it succeeded.
+    fn run(&self, call: &ContractCall, timeout: Duration) -> Result<(ExecutionResult, Option<Journal>)> {
+        let (contract, module) = match (self.contracts.get(&call.contract_id), self.modules.get(&call.contract_id)) {
+            (Some(contract), Some(module)) => (contract, module),
+            _ => {
+                return Err(BlockchainError::InvalidTransaction {
+                    message: format!("Contract not found: {}", call.contract_id),
+                })
+            }
+        };
+
         if call.gas_limit > contract.gas_limit {
             return Err(BlockchainError::InvalidTransaction {
                 message: "Gas limit exceeds contract maximum".to_string(),
             });
         }
+        if call.gas_limit > self.config.max_gas_limit {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!(
+                    "Gas limit {} exceeds the maximum of {}",
+                    call.gas_limit, self.config.max_gas_limit
+                ),
+            });
+        }
 
-        // Simplified contract execution simulation
-        let execution_time = start_time.elapsed();
-        if execution_time > self.execution_timeout {
-            warn!("Contract execution exceeded timeout: {:?}", execution_time);
+        let mut journal = Journal::default();
+        if call.value != 0.0 {
+            journal.adjust_balance(&self.contract_states, &call.contract_id, call.value);
         }
+        let mut store = Store::new(
+            &self.engine,
+            HostState {
+                contract_id: call.contract_id.clone(),
+                caller: call.caller.clone(),
+                value: call.value,
+                block: self.block,
+                base: Arc::clone(&self.contract_states),
+                journal,
+                logs: Vec::new(),
+                events: Vec::new(),
+                transfers: Vec::new(),
+                call_stack: vec![call.contract_id.clone()],
+                linker: Arc::clone(&self.linker),
+                contracts: Arc::clone(&self.contracts),
+                modules: Arc::clone(&self.modules),
+                max_call_depth: self.config.max_call_depth,
+                allow_reentrancy: self.config.allow_reentrancy,
+                limiter: StoreLimiter {
+                    max_memory: self.max_memory,
+                    memory: 0,
+                },
+            },
+        );
+        store.set_fuel(call.gas_limit).map_err(wasm_error)?;
+        store.limiter(|state| &mut state.limiter);
+        let ticks = timeout.as_millis().div_ceil(EPOCH_TICK.as_millis()).max(1);
+        store.set_epoch_deadline(ticks as u64);
+        store.epoch_deadline_trap();
+
+        let outcome = execute(&mut store, module, contract, &call.function_name, &call.args);
+        let gas_used = call.gas_limit - store.get_fuel().unwrap_or(0);
 
-        // Return a simulated successful result
-        Ok(ExecutionResult {
-            success: true,
-            return_value: Some(ContractValue::I32(42)),
-            gas_used: 1000,
-            logs: vec![format!("Simulated call to {}", call.function_name)],
-            events: vec![],
-            error: None,
+        let mut host = store.into_data();
+        Ok(match outcome {
+            Ok(return_value) => {
+                let result = ExecutionResult {
+                    success: true,
+                    return_value,
+                    gas_used,
+                    logs: std::mem::take(&mut host.logs),
+                    events: std::mem::take(&mut host.events),
+                    error: None,
+                    transfers: std::mem::take(&mut host.transfers),
+                };
+                (result, Some(host.journal))
+            }
+            Err(e) => {
+                debug!("Contract call {}::{} failed: {:#}", call.contract_id, call.function_name, e);
+                let result = ExecutionResult {
+                    success: false,
+                    return_value: None,
+                    gas_used,
+                    logs: host.logs,
+                    events: vec![],
+                    error: Some(execution_error(&e)),
+                    transfers: vec![],
+                };
+                (result, None)
+            }
         })
     }
 
-    // Simplified host functions for the demo
     fn validate_contract(&self, contract: &SmartContract) -> Result<()> {
-        // Basic validation
         if contract.name.is_empty() {
             return Err(BlockchainError::InvalidBlock {
                 message: "Contract name cannot be empty".to_string(),
             });
         }
 
-        if contract.code.is_empty() {
+        self.validate_code(&contract.code)
+    }
+
+    /// Checks that `code` is a valid module that every node will execute the
+    /// same way, reporting all violations at once.
+    fn validate_code(&self, code: &[u8]) -> Result<()> {
+        if code.is_empty() {
             return Err(BlockchainError::InvalidBlock {
                 message: "Contract code cannot be empty".to_string(),
             });
         }
 
         // Check if contract code size exceeds memory limit
-        if contract.code.len() > self.max_memory {
+        if code.len() > self.max_memory {
             return Err(BlockchainError::InvalidBlock {
                 message: format!(
                     "Contract code size ({} bytes) exceeds maximum memory limit ({} bytes)",
-                    contract.code.len(),
+                    code.len(),
                     self.max_memory
                 ),
             });
         }
 
-        Ok(())
+        wasmparser::Validator::new().validate_all(code).map_err(invalid_wasm)?;
+
+        let mut problems = determinism_problems(code, self.max_memory)?;
+        if self.config.float_ops == FloatOps::Reject {
+            let features = wasmparser::WasmFeatures::default().difference(wasmparser::WasmFeatures::FLOATS);
+            if let Err(e) = wasmparser::Validator::new_with_features(features).validate_all(code) {
+                problems.push(format!("floating-point types and instructions are not allowed ({})", e.message()));
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(BlockchainError::InvalidBlock {
+                message: format!("Contract is not deterministic: {}", problems.join("; ")),
+            })
+        }
+    }
+
+    /// Sets the block seen by `block_index` and `block_timestamp` in
+    /// subsequent calls.
+    pub fn set_block_context(&mut self, block: BlockContext) {
+        self.block = block;
+    }
+
+    /// Executes the contract transactions of a confirmed block in order. A
+    /// failing call does not invalidate the block; its result records the error.
+    pub fn apply_block(&mut self, block: &Block) -> Vec<ContractReceipt> {
+        self.set_block_context(BlockContext::from(block));
+        block
+            .transactions
+            .iter()
+            .filter_map(|tx| {
+                self.apply_transaction(tx).map(|result| ContractReceipt {
+                    transaction_id: tx.id.clone(),
+                    result,
+                })
+            })
+            .collect()
+    }
+
+    /// Runs a contract deployment or call, or returns `None` for other kinds.
+    pub fn apply_transaction(&mut self, transaction: &Transaction) -> Option<ExecutionResult> {
+        let result = match &transaction.kind {
+            TransactionKind::ContractDeploy { name, code, abi, gas_limit } => {
+                let contract = SmartContract::new(
+                    transaction.to.clone(),
+                    name.clone(),
+                    code.clone(),
+                    abi.clone(),
+                    transaction.from.clone(),
+                    *gas_limit,
+                );
+                self.deploy_contract(contract).map(|()| ExecutionResult {
+                    success: true,
+                    return_value: None,
+                    gas_used: 0,
+                    logs: vec![format!("Deployed contract {}", transaction.to)],
+                    events: vec![],
+                    error: None,
+                    transfers: vec![],
+                })
+            }
+            TransactionKind::ContractCall { function, args, gas_limit } => self.call_contract(ContractCall {
+                contract_id: transaction.to.clone(),
+                function_name: function.clone(),
+                args: args.clone(),
+                caller: transaction.from.clone(),
+                value: transaction.amount,
+                gas_limit: *gas_limit,
+            }),
+            _ => return None,
+        };
+
+        Some(result.unwrap_or_else(|e| {
+            warn!("Contract transaction {} failed: {}", transaction.id, e);
+            ExecutionResult {
+                success: false,
+                return_value: None,
+                gas_used: 0,
+                logs: vec![],
+                events: vec![],
+                error: Some(e.to_string()),
+                transfers: vec![],
+            }
+        }))
+    }
+
+    pub fn config(&self) -> &ContractsConfig {
+        &self.config
     }
 
     pub fn get_contract(&self, contract_id: &str) -> Option<&SmartContract> {
@@ -220,13 +958,18 @@ impl ContractEngine {
     }
 
     pub fn update_contract_balance(&mut self, contract_id: &str, amount: f64) -> Result<()> {
-        let state = self.contract_states.get_mut(contract_id).ok_or_else(|| {
+        let state = Arc::make_mut(&mut self.contract_states).get_mut(contract_id).ok_or_else(|| {
             BlockchainError::InvalidTransaction {
                 message: "Contract not found".to_string(),
             }
         })?;
 
+        self.undo.push(Undo::Balance {
+            contract_id: contract_id.to_string(),
+            previous: state.balance,
+        });
         state.balance += amount;
+        self.changes.balances.insert(contract_id.to_string(), state.balance);
         Ok(())
     }
 
@@ -235,6 +978,560 @@ impl ContractEngine {
     }
 }
 
+/// Lists the imports, start function and memories of a valid module that
+/// could make its execution differ between nodes.
+fn determinism_problems(code: &[u8], max_memory: usize) -> Result<Vec<String>> {
+    use wasmparser::{ExternalKind, Payload, TypeRef};
+
+    let mut problems = Vec::new();
+    let mut memories = 0;
+    let mut memory_exported = false;
+
+    for payload in wasmparser::Parser::new(0).parse_all(code) {
+        match payload.map_err(invalid_wasm)? {
+            Payload::ImportSection(imports) => {
+                for import in imports {
+                    let import = import.map_err(invalid_wasm)?;
+                    let name = format!("{}::{}", import.module, import.name);
+                    if import.module != HOST_MODULE {
+                        problems.push(format!("import '{}' is not allowed; contracts may only import from '{}'", name, HOST_MODULE));
+                    } else if !matches!(import.ty, TypeRef::Func(_)) {
+                        problems.push(format!("import '{}' is not a function", name));
+                    } else if !HOST_FUNCTIONS.contains(&import.name) {
+                        problems.push(format!("'{}' is not a host function", name));
+                    }
+                }
+            }
+            Payload::StartSection { .. } => {
+                problems.push("start functions are not allowed; initialize state in an exported function".to_string());
+            }
+            Payload::MemorySection(section) => {
+                for memory in section {
+                    let memory = memory.map_err(invalid_wasm)?;
+                    memories += 1;
+                    if memory.memory64 || memory.shared {
+                        problems.push("64-bit and shared memories are not allowed".to_string());
+                    }
+                    if memory.initial.saturating_mul(65_536) > max_memory as u64 {
+                        problems.push(format!(
+                            "initial memory of {} pages exceeds the limit of {} bytes",
+                            memory.initial, max_memory
+                        ));
+                    }
+                }
+            }
+            Payload::ExportSection(exports) => {
+                for export in exports {
+                    let export = export.map_err(invalid_wasm)?;
+                    memory_exported |= export.kind == ExternalKind::Memory && export.name == "memory";
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if memories > 1 {
+        problems.push(format!("only one memory is allowed, found {}", memories));
+    }
+    if memories > 0 && !memory_exported {
+        problems.push("the module's memory must be exported as 'memory'".to_string());
+    }
+
+    Ok(problems)
+}
+
+fn invalid_wasm(error: wasmparser::BinaryReaderError) -> BlockchainError {
+    BlockchainError::InvalidBlock {
+        message: format!("Invalid WASM bytecode: {}", error),
+    }
+}
+
+/// Reads the ABI from the module's `abi` custom section, if it has one.
+fn embedded_abi(code: &[u8]) -> Result<Option<ContractABI>> {
+    for payload in wasmparser::Parser::new(0).parse_all(code) {
+        let payload = payload.map_err(|e| BlockchainError::InvalidBlock {
+            message: format!("Invalid WASM bytecode: {}", e),
+        })?;
+        if let wasmparser::Payload::CustomSection(section) = payload {
+            if section.name() == ABI_SECTION {
+                let abi = serde_json::from_slice(section.data()).map_err(|e| BlockchainError::InvalidBlock {
+                    message: format!("Invalid ABI in '{}' section: {}", ABI_SECTION, e),
+                })?;
+                return Ok(Some(abi));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Checks that every ABI function is exported with the matching WASM
+/// signature, reporting all mismatches together.
+fn check_exports(module: &Module, abi: &ContractABI) -> Result<()> {
+    let mut problems = Vec::new();
+
+    for function in &abi.functions {
+        let params: Vec<ValType> = function.inputs.iter().flat_map(ParamType::wasm_params).collect();
+        let results: Vec<ValType> = function.outputs.iter().map(ParamType::wasm_result).collect();
+
+        match module.get_export(&function.name) {
+            Some(ExternType::Func(ty)) => {
+                if !same_types(ty.params(), &params) || !same_types(ty.results(), &results) {
+                    problems.push(format!(
+                        "'{}' is exported as {} but the ABI expects ({:?}) -> ({:?})",
+                        function.name, ty, params, results
+                    ));
+                }
+            }
+            Some(_) => problems.push(format!("'{}' is not an exported function", function.name)),
+            None => problems.push(format!("'{}' is not exported", function.name)),
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(BlockchainError::InvalidBlock {
+            message: format!("Contract does not match its ABI: {}", problems.join("; ")),
+        })
+    }
+}
+
+fn same_types(actual: impl ExactSizeIterator<Item = ValType>, expected: &[ValType]) -> bool {
+    actual.len() == expected.len()
+        && actual.zip(expected).all(|(actual, expected)| ValType::eq(&actual, expected))
+}
+
+fn host_linker(engine: &Engine) -> wasmtime::Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine);
+
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_get",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32, out_ptr: i32, out_cap: i32| {
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            let state = caller.data();
+            match state.journal.get(&state.base, &state.contract_id, &key).map(value_bytes) {
+                Some(value) => write_bytes(&mut caller, out_ptr, out_cap, &value),
+                None => Ok(-1),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_set",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32| {
+            charge(&mut caller, STORAGE_WRITE_GAS)?;
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            let value = read_bytes(&mut caller, value_ptr, value_len)?;
+            let state = caller.data_mut();
+            state.journal.set(&state.base, &state.contract_id, key, Some(ContractValue::Bytes(value)));
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "storage_delete",
+        |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| {
+            charge(&mut caller, STORAGE_WRITE_GAS)?;
+            let key = read_string(&mut caller, key_ptr, key_len)?;
+            let state = caller.data_mut();
+            let existed = state.journal.set(&state.base, &state.contract_id, key, None);
+            Ok(existed as i32)
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "caller",
+        |mut caller: Caller<'_, HostState>, out_ptr: i32, out_cap: i32| {
+            let address = caller.data().caller.clone();
+            write_bytes(&mut caller, out_ptr, out_cap, address.as_bytes())
+        },
+    )?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "self_address",
+        |mut caller: Caller<'_, HostState>, out_ptr: i32, out_cap: i32| {
+            let address = caller.data().contract_id.clone();
+            write_bytes(&mut caller, out_ptr, out_cap, address.as_bytes())
+        },
+    )?;
+    linker.func_wrap(HOST_MODULE, "value", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, 0)?;
+        Ok(caller.data().value)
+    })?;
+    linker.func_wrap(HOST_MODULE, "balance", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, 0)?;
+        let state = caller.data();
+        Ok(state.journal.balance(&state.base, &state.contract_id))
+    })?;
+    linker.func_wrap(HOST_MODULE, "block_index", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, 0)?;
+        Ok(caller.data().block.index as i64)
+    })?;
+    linker.func_wrap(HOST_MODULE, "block_timestamp", |mut caller: Caller<'_, HostState>| {
+        charge(&mut caller, 0)?;
+        Ok(caller.data().block.timestamp)
+    })?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "emit_event",
+        |mut caller: Caller<'_, HostState>, name_ptr: i32, name_len: i32, data_ptr: i32, data_len: i32| {
+            let name = read_string(&mut caller, name_ptr, name_len)?;
+            let data = read_bytes(&mut caller, data_ptr, data_len)?;
+            caller.data_mut().events.push(ContractEvent {
+                name,
+                data: vec![ContractValue::Bytes(data)],
+            });
+            Ok(())
+        },
+    )?;
+    linker.func_wrap(HOST_MODULE, "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let message = read_string(&mut caller, ptr, len)?;
+        debug!("Contract {} log: {}", caller.data().contract_id, message);
+        caller.data_mut().logs.push(message);
+        Ok(())
+    })?;
+    linker.func_wrap(
+        HOST_MODULE,
+        "call",
+        |mut caller: Caller<'_, HostState>,
+         address_ptr: i32,
+         address_len: i32,
+         function_ptr: i32,
+         function_len: i32,
+         args_ptr: i32,
+         args_len: i32,
+         value: f64,
+         gas: i64,
+         out_ptr: i32,
+         out_cap: i32| {
+            let contract_id = read_string(&mut caller, address_ptr, address_len)?;
+            let function_name = read_string(&mut caller, function_ptr, function_len)?;
+            let args = read_bytes(&mut caller, args_ptr, args_len)?;
+            match nested_call(&mut caller, &contract_id, &function_name, &args, value, gas)? {
+                Ok(Some(result)) => write_bytes(&mut caller, out_ptr, out_cap, &value_bytes(&result)),
+                Ok(None) => Ok(0),
+                Err(reason) => {
+                    let message = format!("Call to {}::{} failed: {}", contract_id, function_name, reason);
+                    debug!("Contract {}: {}", caller.data().contract_id, message);
+                    caller.data_mut().logs.push(message);
+                    Ok(-1)
+                }
+            }
+        },
+    )?;
+
+    Ok(linker)
+}
+
+/// Runs a contract-to-contract call from the current frame, in the same
+/// store. An `Err` aborts the calling contract; `Ok(Err(reason))` means the
+/// callee failed or could not be called, and its writes, events and value
+/// transfer were reverted.
+fn nested_call(
+    caller: &mut Caller<'_, HostState>,
+    contract_id: &str,
+    function_name: &str,
+    args: &[u8],
+    value: f64,
+    gas: i64,
+) -> wasmtime::Result<std::result::Result<Option<ContractValue>, String>> {
+    if let Err(reason) = check_nested_call(caller.data(), contract_id, value) {
+        return Ok(Err(reason));
+    }
+
+    let contracts = Arc::clone(&caller.data().contracts);
+    let modules = Arc::clone(&caller.data().modules);
+    let (contract, module) = match (contracts.get(contract_id), modules.get(contract_id)) {
+        (Some(contract), Some(module)) => (contract, module),
+        _ => return Ok(Err(format!("Contract not found: {}", contract_id))),
+    };
+    let args = if args.is_empty() {
+        Ok(vec![])
+    } else {
+        serde_json::from_slice::<Vec<serde_json::Value>>(args)
+            .map_err(|e| format!("Arguments are not a JSON array: {}", e))
+            .and_then(|args| contract.parse_args(function_name, &args).map_err(|e| e.to_string()))
+    };
+    let args = match args {
+        Ok(args) => args,
+        Err(reason) => return Ok(Err(reason)),
+    };
+
+    // The callee gets at most `gas` and its own gas limit; unused gas is returned
+    let remaining = caller.get_fuel()?;
+    let forwarded = if gas > 0 { remaining.min(gas as u64) } else { remaining }.min(contract.gas_limit);
+    caller.set_fuel(forwarded)?;
+
+    let state = caller.data_mut();
+    let checkpoint = state.journal.checkpoint();
+    let events = state.events.len();
+    let transfers = state.transfers.len();
+    if value != 0.0 {
+        state.journal.adjust_balance(&state.base, &state.contract_id, -value);
+        state.journal.adjust_balance(&state.base, contract_id, value);
+        state.transfers.push(ValueTransfer {
+            from: state.contract_id.clone(),
+            to: contract_id.to_string(),
+            amount: value,
+        });
+    }
+    let outer_caller = std::mem::replace(&mut state.caller, state.contract_id.clone());
+    let outer_contract = std::mem::replace(&mut state.contract_id, contract_id.to_string());
+    let outer_value = std::mem::replace(&mut state.value, value);
+    state.call_stack.push(contract_id.to_string());
+
+    let outcome = execute(&mut *caller, module, contract, function_name, &args);
+
+    let state = caller.data_mut();
+    state.call_stack.pop();
+    state.contract_id = outer_contract;
+    state.caller = outer_caller;
+    state.value = outer_value;
+
+    let used = forwarded - caller.get_fuel()?;
+    caller.set_fuel(remaining - used)?;
+
+    Ok(outcome.map_err(|e| {
+        let state = caller.data_mut();
+        state.journal.revert_to(checkpoint);
+        state.events.truncate(events);
+        state.transfers.truncate(transfers);
+        execution_error(&e)
+    }))
+}
+
+/// Applies the call depth, reentrancy and balance rules to a nested call.
+fn check_nested_call(state: &HostState, contract_id: &str, value: f64) -> std::result::Result<(), String> {
+    if state.call_stack.len() > state.max_call_depth {
+        return Err(format!("Maximum call depth of {} exceeded", state.max_call_depth));
+    }
+    if !state.allow_reentrancy && state.call_stack.iter().any(|id| id == contract_id) {
+        return Err(format!("Reentrant call to {}", contract_id));
+    }
+    if !value.is_finite() || value < 0.0 {
+        return Err(format!("Invalid call value {}", value));
+    }
+    let balance = state.journal.balance(&state.base, &state.contract_id);
+    if value > balance {
+        return Err(format!("Cannot send {} with a balance of {}", value, balance));
+    }
+    Ok(())
+}
+
+/// Charges `HOST_CALL_GAS` plus `extra` against the remaining fuel.
+fn charge(caller: &mut Caller<'_, HostState>, extra: u64) -> wasmtime::Result<()> {
+    let cost = HOST_CALL_GAS.saturating_add(extra);
+    let fuel = caller.get_fuel()?;
+    if fuel < cost {
+        caller.set_fuel(0)?;
+        return Err(Trap::OutOfFuel.into());
+    }
+    caller.set_fuel(fuel - cost)
+}
+
+fn guest_memory(caller: &mut Caller<'_, HostState>) -> wasmtime::Result<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Ok(memory),
+        _ => Err(wasmtime::Error::msg("Contract does not export its memory")),
+    }
+}
+
+/// Guest pointers and lengths are unsigned 32-bit values.
+fn guest_range(ptr: i32, len: i32) -> std::ops::Range<usize> {
+    let start = ptr as u32 as usize;
+    start..start + len as u32 as usize
+}
+
+fn read_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<Vec<u8>> {
+    charge(caller, len as u32 as u64 * HOST_BYTE_GAS)?;
+    let memory = guest_memory(caller)?;
+    memory
+        .data(&*caller)
+        .get(guest_range(ptr, len))
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| wasmtime::Error::msg("Host call read out of bounds memory"))
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> wasmtime::Result<String> {
+    String::from_utf8(read_bytes(caller, ptr, len)?)
+        .map_err(|_| wasmtime::Error::msg("Host call argument is not valid UTF-8"))
+}
+
+/// Copies as much of `bytes` as fits in `cap` and returns the full length, so
+/// the guest can retry with a larger buffer.
+fn write_bytes(caller: &mut Caller<'_, HostState>, ptr: i32, cap: i32, bytes: &[u8]) -> wasmtime::Result<i32> {
+    let len = bytes.len().min(cap as u32 as usize);
+    charge(caller, len as u64 * HOST_BYTE_GAS)?;
+    let memory = guest_memory(caller)?;
+    memory
+        .data_mut(&mut *caller)
+        .get_mut(guest_range(ptr, len as i32))
+        .ok_or_else(|| wasmtime::Error::msg("Host call wrote out of bounds memory"))?
+        .copy_from_slice(&bytes[..len]);
+    Ok(bytes.len() as i32)
+}
+
+/// Raw bytes of a stored value; numbers are little-endian.
+fn value_bytes(value: &ContractValue) -> Vec<u8> {
+    match value {
+        ContractValue::U32(v) => v.to_le_bytes().to_vec(),
+        ContractValue::U64(v) => v.to_le_bytes().to_vec(),
+        ContractValue::I32(v) => v.to_le_bytes().to_vec(),
+        ContractValue::I64(v) => v.to_le_bytes().to_vec(),
+        ContractValue::F32(v) => v.to_le_bytes().to_vec(),
+        ContractValue::F64(v) => v.to_le_bytes().to_vec(),
+        ContractValue::String(s) | ContractValue::Address(s) => s.as_bytes().to_vec(),
+        ContractValue::Bytes(bytes) => bytes.clone(),
+    }
+}
+
+/// Lowers an argument to WASM values, copying strings, bytes and addresses
+/// into guest memory obtained from the contract's `alloc` export.
+fn to_wasm_values(mut store: impl AsContextMut<Data = HostState>, instance: &Instance, value: &ContractValue) -> wasmtime::Result<Vec<Val>> {
+    let bytes = match value {
+        ContractValue::U32(v) => return Ok(vec![Val::I32(*v as i32)]),
+        ContractValue::I32(v) => return Ok(vec![Val::I32(*v)]),
+        ContractValue::U64(v) => return Ok(vec![Val::I64(*v as i64)]),
+        ContractValue::I64(v) => return Ok(vec![Val::I64(*v)]),
+        ContractValue::F32(v) => return Ok(vec![Val::F32(v.to_bits())]),
+        ContractValue::F64(v) => return Ok(vec![Val::F64(v.to_bits())]),
+        ContractValue::String(s) | ContractValue::Address(s) => s.as_bytes(),
+        ContractValue::Bytes(bytes) => bytes.as_slice(),
+    };
+
+    let alloc = instance
+        .get_typed_func::<i32, i32>(&mut store, ALLOC_EXPORT)
+        .map_err(|_| {
+            wasmtime::Error::msg(format!(
+                "Contract must export {}(len: i32) -> i32 to take {:?} arguments",
+                ALLOC_EXPORT,
+                value.param_type()
+            ))
+        })?;
+    let memory = instance
+        .get_memory(&mut store, "memory")
+        .ok_or_else(|| wasmtime::Error::msg("Contract does not export its memory"))?;
+
+    let len = i32::try_from(bytes.len()).map_err(|_| wasmtime::Error::msg("Argument is too large"))?;
+    let ptr = alloc.call(&mut store, len)?;
+    memory
+        .write(&mut store, ptr as u32 as usize, bytes)
+        .map_err(|_| wasmtime::Error::msg(format!("{} returned an out of bounds pointer", ALLOC_EXPORT)))?;
+
+    Ok(vec![Val::I32(ptr), Val::I32(len)])
+}
+
+fn from_wasm_value(
+    mut store: impl AsContextMut<Data = HostState>,
+    instance: &Instance,
+    value: &Val,
+    output: Option<&ParamType>,
+) -> wasmtime::Result<ContractValue> {
+    Ok(match (value, output) {
+        (Val::I64(packed), Some(ty @ (ParamType::String | ParamType::Bytes | ParamType::Address))) => {
+            let memory = instance
+                .get_export(&mut store, "memory")
+                .and_then(Extern::into_memory)
+                .ok_or_else(|| wasmtime::Error::msg("Contract does not export its memory"))?;
+            let range = guest_range((*packed >> 32) as i32, *packed as i32);
+            let bytes = memory
+                .data(&store)
+                .get(range)
+                .ok_or_else(|| wasmtime::Error::msg("Contract returned an out of bounds pointer"))?
+                .to_vec();
+
+            match ty {
+                ParamType::Bytes => ContractValue::Bytes(bytes),
+                _ => {
+                    let s = String::from_utf8(bytes)
+                        .map_err(|_| wasmtime::Error::msg(format!("Contract returned a {:?} that is not valid UTF-8", ty)))?;
+                    if *ty == ParamType::String {
+                        ContractValue::String(s)
+                    } else {
+                        ContractValue::Address(s)
+                    }
+                }
+            }
+        }
+        (Val::I32(v), Some(ParamType::U32)) => ContractValue::U32(*v as u32),
+        (Val::I32(v), _) => ContractValue::I32(*v),
+        (Val::I64(v), Some(ParamType::U64)) => ContractValue::U64(*v as u64),
+        (Val::I64(v), _) => ContractValue::I64(*v),
+        (Val::F32(bits), _) => ContractValue::F32(f32::from_bits(*bits)),
+        (Val::F64(bits), _) => ContractValue::F64(f64::from_bits(*bits)),
+        (other, _) => {
+            return Err(wasmtime::Error::msg(format!(
+                "Unsupported WASM return value {:?}",
+                other
+            )))
+        }
+    })
+}
+
+/// Advances `engine`'s epoch every `EPOCH_TICK` until the engine is dropped,
+/// so calls past their deadline are interrupted.
+fn spawn_epoch_ticker(engine: &Engine) {
+    let engine = engine.weak();
+    std::thread::spawn(move || loop {
+        std::thread::sleep(EPOCH_TICK);
+        match engine.upgrade() {
+            Some(engine) => engine.increment_epoch(),
+            None => break,
+        }
+    });
+}
+
+/// Instantiates `module` in `store` and calls its export `function_name`.
+/// Nested calls run in the caller's store, so they share its fuel and host
+/// state.
+fn execute(
+    mut store: impl AsContextMut<Data = HostState>,
+    module: &Module,
+    contract: &SmartContract,
+    function_name: &str,
+    args: &[ContractValue],
+) -> wasmtime::Result<Option<ContractValue>> {
+    let signature = contract.callable_function(function_name, store.as_context().data().value)?;
+    signature.check_args(args)?;
+
+    let linker = Arc::clone(&store.as_context().data().linker);
+    let instance = linker.instantiate(&mut store, module)?;
+    let func = instance
+        .get_func(&mut store, function_name)
+        .ok_or_else(|| wasmtime::Error::msg(format!("Contract has no exported function '{}'", function_name)))?;
+
+    let mut params = Vec::with_capacity(args.len());
+    for arg in args {
+        params.extend(to_wasm_values(&mut store, &instance, arg)?);
+    }
+    let mut results = vec![Val::I32(0); func.ty(&store).results().len()];
+    func.call(&mut store, &params, &mut results)?;
+
+    // The ABI tells signed from unsigned integers and pointers from numbers
+    let output = signature.outputs.first();
+    results
+        .first()
+        .map(|value| from_wasm_value(&mut store, &instance, value, output))
+        .transpose()
+}
+
+fn execution_error(error: &wasmtime::Error) -> String {
+    match error.downcast_ref::<Trap>() {
+        Some(Trap::OutOfFuel) => "Out of gas".to_string(),
+        Some(Trap::Interrupt) => "Execution timed out".to_string(),
+        Some(trap) => format!("Contract trapped: {}", trap),
+        // Host function errors come wrapped in a WASM backtrace
+        None => error.root_cause().to_string(),
+    }
+}
+
+fn wasm_error(error: wasmtime::Error) -> BlockchainError {
+    BlockchainError::InvalidTransaction {
+        message: format!("WASM runtime error: {}", error),
+    }
+}
 
 impl SmartContract {
     pub fn new(
@@ -256,6 +1553,31 @@ impl SmartContract {
         }
     }
 
+    /// The ABI entry of `function` if it can be called with `value`: only
+    /// functions in the ABI can be called, and only payable ones take value.
+    pub fn callable_function(&self, function: &str, value: f64) -> Result<&FunctionSignature> {
+        let signature = self.abi.function(function).ok_or_else(|| BlockchainError::InvalidTransaction {
+            message: format!("'{}' is not in the contract's ABI", function),
+        })?;
+        if value != 0.0 && !signature.payable {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("'{}' is not payable", function),
+            });
+        }
+        Ok(signature)
+    }
+
+    /// Types JSON arguments with the function's ABI entry. Functions missing
+    /// from the ABI cannot be called.
+    pub fn parse_args(&self, function: &str, args: &[serde_json::Value]) -> Result<Vec<ContractValue>> {
+        match self.abi.function(function) {
+            Some(signature) => signature.parse_args(args),
+            None => Err(BlockchainError::InvalidTransaction {
+                message: format!("'{}' is not in the contract's ABI", function),
+            }),
+        }
+    }
+
     pub fn simple_storage_contract() -> Self {
         let abi = ContractABI {
             functions: vec![
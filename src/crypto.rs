@@ -1,11 +1,16 @@
-use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use ed25519_dalek::{Signature, Signer as _, SigningKey, Verifier, VerifyingKey};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::sync::Arc;
 
+use crate::address::{Address, Network};
 use crate::errors::{BlockchainError, Result};
+use crate::signer::Signer;
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Deliberately not `Serialize`: the signing key must never end up in an API
+/// response or log by accident. Persist keys through `keystore::EncryptedKey`.
+#[derive(Clone)]
 pub struct KeyPair {
     pub public_key: PublicKey,
     signing_key: SigningKey,
@@ -91,8 +96,15 @@ impl PublicKey {
         Ok(PublicKey(verifying_key))
     }
 
+    pub fn from_hex(hex_str: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_str).map_err(|_| BlockchainError::InvalidTransaction {
+            message: "Invalid public key format".to_string(),
+        })?;
+        Self::from_bytes(&bytes)
+    }
+
     pub fn to_address(&self) -> String {
-        hex::encode(&self.to_bytes()[..8])
+        Address::from_public_key(self, Network::default()).to_string()
     }
 }
 
@@ -114,6 +126,48 @@ impl DigitalSignature {
         let signature = Signature::from_bytes(&sig_bytes);
         Ok(DigitalSignature(signature))
     }
+
+    pub fn from_hex(hex_str: &str) -> Result<Self> {
+        let bytes = hex::decode(hex_str).map_err(|_| BlockchainError::InvalidTransaction {
+            message: "Invalid signature format".to_string(),
+        })?;
+        Self::from_bytes(&bytes)
+    }
+}
+
+/// One signature to check as part of a batch.
+pub struct SignatureCheck<'a> {
+    pub message: &'a [u8],
+    pub signature: &'a DigitalSignature,
+    pub public_key: &'a PublicKey,
+}
+
+/// Verifies all signatures in one batched multiscalar multiplication, which is
+/// much faster than verifying them one at a time. If the batch fails, each
+/// signature is checked individually and the index of the first invalid one
+/// is returned.
+pub fn verify_batch(checks: &[SignatureCheck]) -> std::result::Result<(), usize> {
+    if checks.is_empty() {
+        return Ok(());
+    }
+
+    let messages: Vec<&[u8]> = checks.iter().map(|check| check.message).collect();
+    let signatures: Vec<Signature> = checks.iter().map(|check| check.signature.0).collect();
+    let verifying_keys: Vec<VerifyingKey> = checks.iter().map(|check| check.public_key.0).collect();
+
+    if ed25519_dalek::verify_batch(&messages, &signatures, &verifying_keys).is_ok() {
+        return Ok(());
+    }
+
+    match checks
+        .iter()
+        .position(|check| !check.public_key.verify(check.message, check.signature))
+    {
+        Some(index) => Err(index),
+        // Batch verification is cofactored and may reject signatures that
+        // verify individually; the individual result is authoritative.
+        None => Ok(()),
+    }
 }
 
 impl fmt::Display for PublicKey {
@@ -140,33 +194,46 @@ impl fmt::Debug for DigitalSignature {
     }
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// A named account that signs through a `Signer`. Not `Serialize`; expose
+/// wallets through public views instead.
+#[derive(Clone)]
 pub struct Wallet {
-    pub keypair: KeyPair,
+    signer: Arc<dyn Signer>,
     pub name: String,
 }
 
 impl Wallet {
     pub fn new(name: String) -> Self {
-        Wallet {
-            keypair: KeyPair::generate(),
-            name,
-        }
+        Self::with_signer(name, Arc::new(KeyPair::generate()))
     }
 
     pub fn from_private_key(name: String, private_key: &[u8]) -> Result<Self> {
-        Ok(Wallet {
-            keypair: KeyPair::from_private_key_bytes(private_key)?,
-            name,
-        })
+        Ok(Self::with_signer(name, Arc::new(KeyPair::from_private_key_bytes(private_key)?)))
+    }
+
+    pub fn with_signer(name: String, signer: Arc<dyn Signer>) -> Self {
+        Wallet { signer, name }
+    }
+
+    pub fn signer(&self) -> Arc<dyn Signer> {
+        self.signer.clone()
+    }
+
+    pub fn public_key(&self) -> &PublicKey {
+        self.signer.public_key()
+    }
+
+    /// The in-process key, or `None` when signing is delegated to a remote signer.
+    pub fn keypair(&self) -> Option<&KeyPair> {
+        self.signer.keypair()
     }
 
     pub fn address(&self) -> String {
-        self.keypair.public_key.to_address()
+        self.public_key().to_address()
     }
 
-    pub fn sign_transaction(&self, transaction_data: &[u8]) -> DigitalSignature {
-        self.keypair.sign(transaction_data)
+    pub fn sign_transaction(&self, transaction_data: &[u8]) -> Result<DigitalSignature> {
+        self.signer.sign(transaction_data)
     }
 }
 
@@ -21,4 +21,10 @@ pub enum BlockchainError {
 
     #[error("Invalid transaction: {message}")]
     InvalidTransaction { message: String },
-}
\ No newline at end of file
+
+    #[error("Wallet error: {message}")]
+    Wallet { message: String },
+
+    #[error("Invalid address {message}")]
+    InvalidAddress { message: String },
+}
@@ -0,0 +1,172 @@
+use crate::crypto::{KeyPair, Wallet};
+use crate::errors::{BlockchainError, Result};
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha512;
+use std::fmt;
+use zeroize::Zeroizing;
+
+type HmacSha512 = Hmac<Sha512>;
+
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+const SLIP10_ED25519_SEED_KEY: &[u8] = b"ed25519 seed";
+
+/// SLIP-44 style coin type used in account derivation paths.
+pub const COIN_TYPE: u32 = 9000;
+
+/// Generates a new BIP39 English mnemonic with `word_count` words
+/// (12, 15, 18, 21 or 24).
+pub fn generate_mnemonic(word_count: usize) -> Result<String> {
+    if !matches!(word_count, 12 | 15 | 18 | 21 | 24) {
+        return Err(BlockchainError::Wallet {
+            message: format!("Unsupported mnemonic length {}, use 12, 15, 18, 21 or 24 words", word_count),
+        });
+    }
+
+    let mut entropy = Zeroizing::new(vec![0u8; word_count * 4 / 3]);
+    OsRng.fill_bytes(&mut entropy);
+
+    let mnemonic = Mnemonic::from_entropy(&entropy).map_err(|e| BlockchainError::Wallet {
+        message: format!("Failed to generate mnemonic: {}", e),
+    })?;
+
+    Ok(mnemonic.to_string())
+}
+
+/// Validates a BIP39 phrase and returns the 64-byte seed derived from it.
+pub fn mnemonic_to_seed(phrase: &str, passphrase: &str) -> Result<Zeroizing<[u8; 64]>> {
+    let mnemonic = Mnemonic::parse(phrase).map_err(|e| BlockchainError::Wallet {
+        message: format!("Invalid mnemonic: {}", e),
+    })?;
+
+    Ok(Zeroizing::new(mnemonic.to_seed(passphrase)))
+}
+
+/// SLIP-10 extended private key for ed25519. Only hardened derivation is
+/// defined for ed25519, so every path segment is treated as hardened.
+#[derive(Clone)]
+pub struct ExtendedKey {
+    key: Zeroizing<[u8; 32]>,
+    chain_code: Zeroizing<[u8; 32]>,
+}
+
+impl ExtendedKey {
+    pub fn master(seed: &[u8]) -> Self {
+        Self::from_hmac(SLIP10_ED25519_SEED_KEY, &[seed])
+    }
+
+    pub fn derive_child(&self, index: u32) -> Self {
+        let index = index | HARDENED_OFFSET;
+        Self::from_hmac(
+            self.chain_code.as_ref(),
+            &[&[0u8], self.key.as_ref(), &index.to_be_bytes()],
+        )
+    }
+
+    /// Derives along a path such as `m/44'/9000'/0'/0'/0'`. The trailing `'`
+    /// is optional since all segments are hardened anyway.
+    pub fn derive_path(&self, path: &str) -> Result<Self> {
+        let mut segments = path.split('/');
+        if segments.next() != Some("m") {
+            return Err(BlockchainError::Wallet {
+                message: format!("Derivation path must start with 'm': {}", path),
+            });
+        }
+
+        let mut key = self.clone();
+        for segment in segments {
+            let index: u32 = segment
+                .trim_end_matches(['\'', 'h', 'H'])
+                .parse()
+                .ok()
+                .filter(|index| *index < HARDENED_OFFSET)
+                .ok_or_else(|| BlockchainError::Wallet {
+                    message: format!("Invalid derivation path segment '{}'", segment),
+                })?;
+            key = key.derive_child(index);
+        }
+
+        Ok(key)
+    }
+
+    pub fn private_key_bytes(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    pub fn chain_code(&self) -> &[u8; 32] {
+        &self.chain_code
+    }
+
+    pub fn keypair(&self) -> Result<KeyPair> {
+        KeyPair::from_private_key_bytes(self.key.as_ref())
+    }
+
+    fn from_hmac(hmac_key: &[u8], data: &[&[u8]]) -> Self {
+        let mut mac = HmacSha512::new_from_slice(hmac_key).expect("HMAC accepts keys of any length");
+        for chunk in data {
+            mac.update(chunk);
+        }
+        let mut output = Zeroizing::new([0u8; 64]);
+        output.copy_from_slice(&mac.finalize().into_bytes());
+
+        let mut key = Zeroizing::new([0u8; 32]);
+        let mut chain_code = Zeroizing::new([0u8; 32]);
+        key.copy_from_slice(&output[..32]);
+        chain_code.copy_from_slice(&output[32..]);
+
+        ExtendedKey { key, chain_code }
+    }
+}
+
+/// A seed from which any number of account wallets can be re-derived.
+pub struct HdWallet {
+    pub name: String,
+    seed: Zeroizing<Vec<u8>>,
+    pub next_index: u32,
+}
+
+impl HdWallet {
+    pub fn from_seed(name: String, seed: &[u8], next_index: u32) -> Self {
+        HdWallet {
+            name,
+            seed: Zeroizing::new(seed.to_vec()),
+            next_index,
+        }
+    }
+
+    pub fn from_mnemonic(name: String, phrase: &str) -> Result<Self> {
+        let seed = mnemonic_to_seed(phrase, "")?;
+        Ok(Self::from_seed(name, seed.as_ref(), 0))
+    }
+
+    pub fn seed(&self) -> &[u8] {
+        &self.seed
+    }
+
+    pub fn derivation_path(index: u32) -> String {
+        format!("m/44'/{}'/{}'/0'/0'", COIN_TYPE, index)
+    }
+
+    pub fn derive_account(&self, index: u32) -> Result<Wallet> {
+        let key = ExtendedKey::master(&self.seed).derive_path(&Self::derivation_path(index))?;
+        Wallet::from_private_key(format!("{} #{}", self.name, index), key.private_key_bytes())
+    }
+
+    /// Derives the account at `next_index` and advances the counter.
+    pub fn derive_next(&mut self) -> Result<(u32, Wallet)> {
+        let index = self.next_index;
+        let wallet = self.derive_account(index)?;
+        self.next_index += 1;
+        Ok((index, wallet))
+    }
+}
+
+impl fmt::Debug for HdWallet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HdWallet")
+            .field("name", &self.name)
+            .field("next_index", &self.next_index)
+            .finish()
+    }
+}
@@ -0,0 +1,246 @@
+use crate::config::KeystoreConfig;
+use crate::crypto::Wallet;
+use crate::errors::{BlockchainError, Result};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tracing::info;
+use zeroize::Zeroizing;
+
+const KEYSTORE_VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+
+/// Argon2id cost parameters, stored alongside each ciphertext so they can be
+/// raised later without breaking existing keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// A private key encrypted with XChaCha20-Poly1305 under an Argon2id-derived key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub version: u8,
+    pub kdf: KdfParams,
+    pub salt: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+impl EncryptedKey {
+    pub fn encrypt(secret: &[u8], password: &str) -> Result<Self> {
+        Self::encrypt_with_params(secret, password, KdfParams::default())
+    }
+
+    pub fn encrypt_with_params(secret: &[u8], password: &str, kdf: KdfParams) -> Result<Self> {
+        if password.is_empty() {
+            return Err(BlockchainError::Wallet {
+                message: "Password cannot be empty".to_string(),
+            });
+        }
+
+        let mut salt = [0u8; SALT_LEN];
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let key = derive_key(password, &salt, &kdf)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(&nonce), secret)
+            .map_err(|_| BlockchainError::Wallet {
+                message: "Failed to encrypt private key".to_string(),
+            })?;
+
+        Ok(EncryptedKey {
+            version: KEYSTORE_VERSION,
+            kdf,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    pub fn decrypt(&self, password: &str) -> Result<Zeroizing<Vec<u8>>> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(BlockchainError::Wallet {
+                message: format!("Unsupported keystore version {}", self.version),
+            });
+        }
+
+        let salt = decode_field("salt", &self.salt)?;
+        let nonce = decode_field("nonce", &self.nonce)?;
+        let ciphertext = decode_field("ciphertext", &self.ciphertext)?;
+
+        if nonce.len() != NONCE_LEN {
+            return Err(BlockchainError::Wallet {
+                message: "Malformed keystore nonce".to_string(),
+            });
+        }
+
+        let key = derive_key(password, &salt, &self.kdf)?;
+        let cipher = XChaCha20Poly1305::new(key.as_ref().into());
+        let secret = cipher
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| BlockchainError::Wallet {
+                message: "Invalid password".to_string(),
+            })?;
+
+        Ok(Zeroizing::new(secret))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(self).map_err(BlockchainError::from)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(BlockchainError::from)
+    }
+
+    /// Reads a keystore file: a wallet export (CLI or API response) or a bare keystore.
+    pub fn read_file(path: impl AsRef<Path>) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+        let keystore = value
+            .pointer("/data/keystore")
+            .or_else(|| value.get("keystore"))
+            .unwrap_or(&value)
+            .clone();
+
+        serde_json::from_value(keystore).map_err(BlockchainError::from)
+    }
+}
+
+fn derive_key(password: &str, salt: &[u8], kdf: &KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| BlockchainError::Wallet {
+            message: format!("Invalid key derivation parameters: {}", e),
+        })?;
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(password.as_bytes(), salt, key.as_mut())
+        .map_err(|e| BlockchainError::Wallet {
+            message: format!("Key derivation failed: {}", e),
+        })?;
+
+    Ok(key)
+}
+
+fn decode_field(name: &str, value: &str) -> Result<Vec<u8>> {
+    hex::decode(value).map_err(|e| BlockchainError::Wallet {
+        message: format!("Malformed keystore {}: {}", name, e),
+    })
+}
+
+struct UnlockedWallet {
+    wallet: Wallet,
+    /// `None` for wallets backed by an external signer, which never expire.
+    expires_at: Option<Instant>,
+}
+
+/// Wallets decrypted in the node's memory, each until its unlock timeout expires.
+pub struct Keyring {
+    config: KeystoreConfig,
+    unlocked: HashMap<String, UnlockedWallet>,
+}
+
+impl Keyring {
+    pub fn new(config: KeystoreConfig) -> Self {
+        Keyring {
+            config,
+            unlocked: HashMap::new(),
+        }
+    }
+
+    /// Keeps `wallet` unlocked for `timeout` (or the configured default), capped
+    /// at the configured maximum. Returns the timeout actually applied.
+    pub fn unlock(&mut self, wallet: Wallet, timeout: Option<Duration>) -> Duration {
+        let max_timeout = Duration::from_secs(self.config.max_unlock_timeout_secs);
+        let timeout = timeout
+            .unwrap_or(Duration::from_secs(self.config.unlock_timeout_secs))
+            .min(max_timeout);
+
+        info!("Wallet {} unlocked for {:?}", wallet.address(), timeout);
+        self.unlocked.insert(
+            wallet.address(),
+            UnlockedWallet {
+                wallet,
+                expires_at: Some(Instant::now() + timeout),
+            },
+        );
+
+        timeout
+    }
+
+    /// Makes a wallet whose key is held by an external signer available for
+    /// signing until it is explicitly locked.
+    pub fn attach(&mut self, wallet: Wallet) {
+        info!("Wallet {} attached to remote signer", wallet.address());
+        self.unlocked.insert(
+            wallet.address(),
+            UnlockedWallet {
+                wallet,
+                expires_at: None,
+            },
+        );
+    }
+
+    pub fn lock(&mut self, address: &str) -> bool {
+        let locked = self.unlocked.remove(address).is_some();
+        if locked {
+            info!("Wallet {} locked", address);
+        }
+        locked
+    }
+
+    pub fn lock_all(&mut self) {
+        self.unlocked.clear();
+    }
+
+    /// Returns the unlocked wallet for `address`, locking it first if its
+    /// timeout has passed.
+    pub fn wallet(&mut self, address: &str) -> Option<&Wallet> {
+        self.purge_expired();
+        self.unlocked.get(address).map(|entry| &entry.wallet)
+    }
+
+    pub fn is_unlocked(&mut self, address: &str) -> bool {
+        self.wallet(address).is_some()
+    }
+
+    pub fn purge_expired(&mut self) {
+        let now = Instant::now();
+        self.unlocked.retain(|address, entry| {
+            let keep = entry.expires_at.is_none_or(|expires_at| expires_at > now);
+            if !keep {
+                info!("Wallet {} unlock timeout expired", address);
+            }
+            keep
+        });
+    }
+}
+
+impl Default for Keyring {
+    fn default() -> Self {
+        Self::new(KeystoreConfig::default())
+    }
+}
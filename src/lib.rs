@@ -1,24 +1,37 @@
+pub mod address;
 pub mod block;
 pub mod blockchain;
 pub mod poh;
 pub mod transaction;
 pub mod errors;
 pub mod cli;
+pub mod client;
 pub mod crypto;
+pub mod keystore;
+pub mod hdwallet;
 pub mod mining;
+pub mod mempool;
+pub mod multisig;
+pub mod signer;
 pub mod storage;
 pub mod network;
 pub mod contracts;
 pub mod api;
 pub mod config;
 
+pub use address::Address;
 pub use block::Block;
 pub use blockchain::Blockchain;
 pub use poh::PohRecorder;
 pub use transaction::Transaction;
 pub use errors::{BlockchainError, Result};
 pub use crypto::{Wallet, KeyPair, PublicKey, DigitalSignature};
+pub use keystore::{EncryptedKey, Keyring};
+pub use hdwallet::HdWallet;
 pub use mining::{Miner, MiningConfig, MiningStats};
+pub use mempool::Mempool;
+pub use multisig::MultisigAccount;
+pub use signer::Signer;
 pub use storage::BlockchainStorage;
 pub use contracts::{SmartContract, ContractEngine};
 pub use api::ApiState;
@@ -27,6 +40,83 @@ pub use config::BlockchainConfig;
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::MempoolConfig;
+
+    fn signed_transfer(wallet: &Wallet, to: &str, amount: f64, fee: f64, nonce: u64) -> Transaction {
+        let mut tx = Transaction::new(wallet.address(), to.to_string(), amount, None).unwrap();
+        tx.fee = fee;
+        tx.nonce = nonce;
+        sign(wallet, tx)
+    }
+
+    /// Genesis credit of `amount` to `address`, for funding test accounts.
+    fn funding(address: &str, amount: f64) -> Transaction {
+        Transaction::new("genesis".to_string(), address.to_string(), amount, None).unwrap()
+    }
+
+    fn sign(wallet: &Wallet, mut tx: Transaction) -> Transaction {
+        tx.signature = Some(wallet.sign_transaction(&tx.signable_data().unwrap()).unwrap());
+        tx.from_public_key = Some(wallet.public_key().clone());
+        tx
+    }
+
+    /// ABI declaring each `(name, inputs, outputs, payable)` function.
+    fn abi(functions: &[(&str, &[contracts::ParamType], &[contracts::ParamType], bool)]) -> contracts::ContractABI {
+        contracts::ContractABI {
+            functions: functions
+                .iter()
+                .map(|(name, inputs, outputs, payable)| contracts::FunctionSignature {
+                    name: name.to_string(),
+                    inputs: inputs.to_vec(),
+                    outputs: outputs.to_vec(),
+                    payable: *payable,
+                    gas_cost: 0,
+                })
+                .collect(),
+            events: vec![],
+        }
+    }
+
+    /// Counter contract used by the contract tests: `increment` bumps a u64
+    /// stored under "count" and emits `Incremented`. Its ABI is embedded, and
+    /// only `increment` and `increment_then_fail` are payable.
+    fn counter_wasm() -> Vec<u8> {
+        use contracts::ParamType::{I32, I64};
+
+        let abi = abi(&[
+            ("increment", &[], &[I64], true),
+            ("increment_then_fail", &[], &[], true),
+            ("caller_len", &[], &[I32], false),
+            ("height", &[], &[I64], false),
+            ("bad_log", &[], &[], false),
+        ]);
+        wat::parse_str(format!(
+            r#"(module
+                (@custom "abi" "{}")
+                (import "nchain" "storage_get" (func $get (param i32 i32 i32 i32) (result i32)))
+                (import "nchain" "storage_set" (func $set (param i32 i32 i32 i32)))
+                (import "nchain" "caller" (func $caller (param i32 i32) (result i32)))
+                (import "nchain" "block_index" (func $block_index (result i64)))
+                (import "nchain" "emit_event" (func $emit (param i32 i32 i32 i32)))
+                (import "nchain" "log" (func $log (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "count")
+                (data (i32.const 16) "Incremented")
+                (func $increment (result i64)
+                    (drop (call $get (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 8)))
+                    (i64.store (i32.const 64) (i64.add (i64.load (i32.const 64)) (i64.const 1)))
+                    (call $set (i32.const 0) (i32.const 5) (i32.const 64) (i32.const 8))
+                    (call $emit (i32.const 16) (i32.const 11) (i32.const 64) (i32.const 8))
+                    (i64.load (i32.const 64)))
+                (func (export "increment") (result i64) (call $increment))
+                (func (export "increment_then_fail") (drop (call $increment)) unreachable)
+                (func (export "caller_len") (result i32) (call $caller (i32.const 128) (i32.const 4)))
+                (func (export "height") (result i64) (call $block_index))
+                (func (export "bad_log") (call $log (i32.const 65530) (i32.const 100))))"#,
+            serde_json::to_string(&abi).unwrap().replace('"', "\\\"")
+        ))
+        .unwrap()
+    }
 
     #[test]
     fn test_transaction_creation() {
@@ -90,14 +180,13 @@ mod tests {
     #[test]
     fn test_add_block() {
         let mut blockchain = Blockchain::new().unwrap();
-        let tx = Transaction::new(
-            "alice".to_string(),
-            "bob".to_string(),
-            50.0,
-            None,
-        ).unwrap();
+        let alice = Wallet::new("alice".to_string());
+        let tx = signed_transfer(&alice, "bob", 50.0, 0.0, 0);
 
-        assert!(blockchain.add_block(vec![tx]).is_ok());
+        let unsigned = Transaction::new(alice.address(), "bob".to_string(), 50.0, None).unwrap();
+        assert!(blockchain.add_block(vec![funding(&alice.address(), 50.0), unsigned]).is_err());
+        assert!(blockchain.add_block(vec![tx.clone()]).is_err());
+        assert!(blockchain.add_block(vec![funding(&alice.address(), 50.0), tx]).is_ok());
         assert_eq!(blockchain.len(), 2);
         assert!(blockchain.is_chain_valid().is_ok());
     }
@@ -106,13 +195,15 @@ mod tests {
     fn test_balance_calculation() {
         let mut blockchain = Blockchain::new().unwrap();
 
-        let tx1 = Transaction::new("genesis".to_string(), "alice".to_string(), 100.0, None).unwrap();
-        let tx2 = Transaction::new("alice".to_string(), "bob".to_string(), 30.0, None).unwrap();
+        let alice = Wallet::new("alice".to_string());
+
+        let tx1 = Transaction::new("genesis".to_string(), alice.address(), 100.0, None).unwrap();
+        let tx2 = signed_transfer(&alice, "bob", 30.0, 0.0, 0);
 
         blockchain.add_block(vec![tx1]).unwrap();
         blockchain.add_block(vec![tx2]).unwrap();
 
-        assert_eq!(blockchain.get_balance("alice"), 70.0);
+        assert_eq!(blockchain.get_balance(&alice.address()), 70.0);
         assert_eq!(blockchain.get_balance("bob"), 30.0);
     }
 
@@ -127,9 +218,1458 @@ mod tests {
         let blockchain = Blockchain::new().unwrap();
         assert!(blockchain.is_chain_valid().is_ok());
 
-        let tx = Transaction::new("alice".to_string(), "bob".to_string(), 50.0, None).unwrap();
+        let alice = Wallet::new("alice".to_string());
+        let tx = signed_transfer(&alice, "bob", 50.0, 0.0, 0);
         let mut blockchain = blockchain;
+        blockchain.add_block(vec![funding(&alice.address(), 50.0), tx]).unwrap();
+        assert!(blockchain.is_chain_valid().is_ok());
+    }
+
+    #[test]
+    fn test_mempool_replace_by_fee() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::new(MempoolConfig::default());
+        let wallet = Wallet::new("alice".to_string());
+        blockchain.add_block(vec![funding(&wallet.address(), 100.0)]).unwrap();
+
+        let original = signed_transfer(&wallet, "bob", 10.0, 1.0, 0);
+        assert!(mempool.add_transaction(original.clone(), &blockchain).unwrap().is_none());
+
+        let too_cheap = signed_transfer(&wallet, "carol", 10.0, 1.05, 0);
+        assert!(mempool.add_transaction(too_cheap, &blockchain).is_err());
+
+        let replacement = signed_transfer(&wallet, "carol", 10.0, 1.2, 0);
+        let replaced = mempool.add_transaction(replacement.clone(), &blockchain).unwrap();
+        assert_eq!(replaced.map(|tx| tx.id), Some(original.id.clone()));
+        assert_eq!(mempool.len(), 1);
+        assert!(mempool.get_transaction(&original.id).is_none());
+        assert!(mempool.get_transaction(&replacement.id).is_some());
+    }
+
+    #[test]
+    fn test_mempool_cancellation() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::new(MempoolConfig::default());
+        let wallet = Wallet::new("alice".to_string());
+        blockchain.add_block(vec![funding(&wallet.address(), 100.0)]).unwrap();
+
+        let original = signed_transfer(&wallet, "bob", 10.0, 1.0, 0);
+        mempool.add_transaction(original.clone(), &blockchain).unwrap();
+        assert_eq!(mempool.pending_nonce(&wallet.address(), &blockchain), 1);
+
+        let cancellation = sign(&wallet, Transaction::cancellation(&original, 2.0).unwrap());
+        assert_eq!(cancellation.to, wallet.address());
+        assert_eq!(cancellation.amount, 0.0);
+
+        let replaced = mempool.add_transaction(cancellation, &blockchain).unwrap();
+        assert_eq!(replaced.map(|tx| tx.id), Some(original.id));
+        assert_eq!(mempool.len(), 1);
+    }
+
+    #[test]
+    fn test_blocks_follow_each_senders_nonce_sequence() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let alice = Wallet::new("alice".to_string());
+        let bob = Wallet::new("bob".to_string()).address();
+        blockchain.add_block(vec![funding(&alice.address(), 100.0)]).unwrap();
+
+        let first = signed_transfer(&alice, &bob, 10.0, 0.1, 0);
+        let cancellation = sign(&alice, Transaction::cancellation(&first, 0.2).unwrap());
+        let second = signed_transfer(&alice, &bob, 10.0, 5.0, 1);
+        let gapped = signed_transfer(&alice, &bob, 10.0, 0.1, 2);
+
+        // A cancelled transfer cannot be confirmed next to its cancellation
+        assert!(blockchain.add_block(vec![first.clone(), cancellation.clone()]).is_err());
+        assert!(blockchain.add_block(vec![second.clone()]).is_err());
+        assert!(blockchain.add_block(vec![first.clone(), gapped.clone()]).is_err());
+        assert!(blockchain.is_chain_valid().is_ok());
+
+        // The higher fee on nonce 1 does not let it jump ahead of nonce 0
+        mempool.add_transaction(second.clone(), &blockchain).unwrap();
+        mempool.add_transaction(first.clone(), &blockchain).unwrap();
+        let ready: Vec<u64> = mempool.ready_transactions(&blockchain, chrono::Utc::now()).iter().map(|tx| tx.nonce).collect();
+        assert_eq!(ready, vec![0, 1]);
+
+        mempool.remove_transaction(&first.id);
+        assert!(mempool.ready_transactions(&blockchain, chrono::Utc::now()).is_empty());
+
+        blockchain.add_block(vec![first, second]).unwrap();
+        assert_eq!(blockchain.next_nonce(&alice.address()), 2);
+        assert!(blockchain.is_chain_valid().is_ok());
+    }
+
+    #[test]
+    fn test_mempool_rejects_unsigned_and_foreign_signatures() {
+        let blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let wallet = Wallet::new("alice".to_string());
+
+        let unsigned = Transaction::new(wallet.address(), "bob".to_string(), 1.0, None).unwrap();
+        assert!(mempool.add_transaction(unsigned, &blockchain).is_err());
+
+        let mut forged = Transaction::new("victim".to_string(), "bob".to_string(), 1.0, None).unwrap();
+        forged = sign(&wallet, forged);
+        assert!(mempool.add_transaction(forged, &blockchain).is_err());
+        assert!(mempool.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mempool_persistence_drops_stale_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = BlockchainStorage::create_file(dir.path().join("mempool.db")).await.unwrap();
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let wallet = Wallet::new("alice".to_string());
+        blockchain.add_block(vec![funding(&wallet.address(), 100.0)]).unwrap();
+
+        let first = signed_transfer(&wallet, "bob", 5.0, 0.1, 0);
+        let second = signed_transfer(&wallet, "bob", 5.0, 0.1, 1);
+        mempool.add_transaction(first.clone(), &blockchain).unwrap();
+        mempool.add_transaction(second.clone(), &blockchain).unwrap();
+
+        let pending: Vec<Transaction> = mempool.transactions().cloned().collect();
+        storage.save_mempool(&pending).await.unwrap();
+
+        // The first transaction gets mined while the node is down.
+        blockchain.add_block(vec![first]).unwrap();
+
+        let mut restored = Mempool::default();
+        let dropped = restored.restore(storage.load_mempool().await.unwrap(), &blockchain);
+        assert_eq!(dropped, 1);
+        assert_eq!(restored.len(), 1);
+        assert!(restored.get_transaction(&second.id).is_some());
+    }
+
+    #[test]
+    fn test_mempool_address_view_and_histogram() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let alice = Wallet::new("alice".to_string());
+        let bob = Wallet::new("bob".to_string());
+
+        blockchain.add_block(vec![funding(&alice.address(), 100.0), funding(&bob.address(), 10.0)]).unwrap();
+
+        mempool.add_transaction(signed_transfer(&alice, &bob.address(), 30.0, 1.0, 0), &blockchain).unwrap();
+        mempool.add_transaction(signed_transfer(&bob, &alice.address(), 5.0, 0.0, 0), &blockchain).unwrap();
+
+        let (outgoing, incoming) = mempool.transactions_for_address(&alice.address());
+        assert_eq!(outgoing.len(), 1);
+        assert_eq!(incoming.len(), 1);
+        assert_eq!(mempool.projected_balance(&alice.address(), &blockchain), 74.0);
+
+        let histogram = mempool.fee_rate_histogram();
+        assert_eq!(histogram.iter().map(|b| b.count).sum::<usize>(), 2);
+        assert_eq!(histogram[0].count, 1);
+        assert_eq!(histogram.iter().map(|b| b.total_size).sum::<usize>(), mempool.total_size());
+
+        let ranked = mempool.transactions_by_fee_rate();
+        assert_eq!(ranked[0].from, alice.address());
+    }
+
+    #[test]
+    fn test_encrypted_key_roundtrip() {
+        let wallet = Wallet::new("alice".to_string());
+        let secret = wallet.keypair().unwrap().to_private_key_bytes();
+
+        let encrypted = EncryptedKey::encrypt(&secret, "correct horse").unwrap();
+        assert!(!encrypted.ciphertext.contains(&hex::encode(secret)));
+        assert_eq!(encrypted.decrypt("correct horse").unwrap().as_slice(), &secret);
+        assert!(encrypted.decrypt("wrong horse").is_err());
+        assert!(EncryptedKey::encrypt(&secret, "").is_err());
+    }
+
+    #[test]
+    fn test_keyring_unlock_timeout() {
+        let mut keyring = Keyring::default();
+        let wallet = Wallet::new("alice".to_string());
+        let address = wallet.address();
+
+        keyring.unlock(wallet.clone(), Some(std::time::Duration::ZERO));
+        assert!(!keyring.is_unlocked(&address));
+
+        keyring.unlock(wallet, None);
+        assert!(keyring.is_unlocked(&address));
+        assert!(keyring.lock(&address));
+        assert!(keyring.wallet(&address).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_wallet_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("legacy.db");
+        let wallet = Wallet::new("legacy".to_string());
+
+        let pool = sqlx::SqlitePool::connect(&format!("{}?mode=rwc", path.display())).await.unwrap();
+        sqlx::query(
+            "CREATE TABLE wallets (address TEXT PRIMARY KEY, name TEXT NOT NULL, public_key TEXT NOT NULL, private_key TEXT NOT NULL, created_at TEXT NOT NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO wallets VALUES (?, ?, ?, ?, ?)")
+            .bind(wallet.address())
+            .bind(&wallet.name)
+            .bind(wallet.public_key().to_string())
+            .bind(hex::encode(wallet.keypair().unwrap().to_private_key_bytes()))
+            .bind(chrono::Utc::now().to_rfc3339())
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let storage = BlockchainStorage::create_file(&path).await.unwrap();
+        assert!(storage.has_plaintext_wallets().await.unwrap());
+        assert!(storage.load_wallet(&wallet.address(), "pw").await.is_err());
+        assert!(storage.save_wallet(&Wallet::new("new".to_string()), "pw").await.is_err());
+
+        assert_eq!(storage.migrate_plaintext_wallets("pw").await.unwrap(), 1);
+        assert!(!storage.has_plaintext_wallets().await.unwrap());
+
+        let loaded = storage.load_wallet(&wallet.address(), "pw").await.unwrap().unwrap();
+        assert_eq!(loaded.address(), wallet.address());
+        assert!(storage.load_wallet(&wallet.address(), "nope").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_wallet_views_do_not_expose_private_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = BlockchainStorage::create_file(dir.path().join("wallets.db")).await.unwrap();
+        let wallet = Wallet::new("alice".to_string());
+        let private_key_hex = hex::encode(wallet.keypair().unwrap().to_private_key_bytes());
+        storage.save_wallet(&wallet, "pw").await.unwrap();
+
+        let info = storage.get_wallet_info(&wallet.address()).await.unwrap().unwrap();
+        let view = serde_json::to_string(&api::WalletView::from_info(info, 0.0)).unwrap();
+        assert!(view.contains(&wallet.public_key().to_string()));
+        assert!(!view.contains(&private_key_hex));
+
+        let keystore = storage.load_encrypted_key(&wallet.address()).await.unwrap().unwrap();
+        assert!(!keystore.to_json().unwrap().contains(&private_key_hex));
+        assert_eq!(hex::encode(keystore.decrypt("pw").unwrap().as_slice()), private_key_hex);
+    }
+
+    #[test]
+    fn test_slip10_ed25519_derivation() {
+        // SLIP-0010 test vector 1 for ed25519
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = hdwallet::ExtendedKey::master(&seed);
+        assert_eq!(
+            hex::encode(master.private_key_bytes()),
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7"
+        );
+        assert_eq!(
+            hex::encode(master.chain_code()),
+            "90046a93de5380a72b5e45010748567d5ea02bbf6522f979e05c0d8d8ca9fffb"
+        );
+
+        let child = master.derive_path("m/0'").unwrap();
+        assert_eq!(
+            hex::encode(child.private_key_bytes()),
+            "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3"
+        );
+        assert_eq!(
+            hex::encode(child.chain_code()),
+            "8b59aa11380b624e81507a27fedda59fea6d0b779a778918a2fd3590e16e9c69"
+        );
+
+        assert!(master.derive_path("0'/1'").is_err());
+        assert!(master.derive_path("m/x'").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hd_wallet_restore_from_mnemonic() {
+        let mnemonic = hdwallet::generate_mnemonic(12).unwrap();
+        assert_eq!(mnemonic.split_whitespace().count(), 12);
+        assert!(HdWallet::from_mnemonic("bad".to_string(), "not a valid phrase").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = BlockchainStorage::create_file(dir.path().join("hd.db")).await.unwrap();
+        let mut original = HdWallet::from_mnemonic("savings".to_string(), &mnemonic).unwrap();
+        let (_, first) = original.derive_next().unwrap();
+        storage.save_hd_wallet(&original, "pw").await.unwrap();
+        assert!(storage.save_hd_wallet(&original, "pw").await.is_err());
+
+        let (index, second) = storage.derive_hd_account("savings", "pw").await.unwrap().unwrap();
+        assert_eq!(index, 1);
+        assert_ne!(first.address(), second.address());
+        assert!(storage.load_wallet(&second.address(), "pw").await.unwrap().is_some());
+        assert!(storage.derive_hd_account("savings", "wrong").await.is_err());
+
+        let (third, fourth) = tokio::join!(
+            storage.derive_hd_account("savings", "pw"),
+            storage.derive_hd_account("savings", "pw"),
+        );
+        let mut indices = [third.unwrap().unwrap().0, fourth.unwrap().unwrap().0];
+        indices.sort();
+        assert_eq!(indices, [2, 3]);
+
+        let restored = HdWallet::from_mnemonic("restored".to_string(), &mnemonic).unwrap();
+        assert_eq!(restored.derive_account(0).unwrap().address(), first.address());
+        assert_eq!(restored.derive_account(1).unwrap().address(), second.address());
+    }
+
+    #[test]
+    fn test_address_checksum_and_validation() {
+        let wallet = Wallet::new("alice".to_string());
+        let address = wallet.address();
+        assert!(address.starts_with("nc1"));
+
+        let parsed: Address = address.parse().unwrap();
+        assert_eq!(parsed.network(), address::Network::Mainnet);
+        assert_eq!(parsed.version(), address::ADDRESS_VERSION_ED25519);
+        assert_eq!(parsed.to_string(), address);
+        assert_eq!(address::validate_address(&address.to_uppercase()).unwrap(), address);
+
+        // A single mistyped character breaks the checksum
+        let mut typo = address.clone().into_bytes();
+        let last = typo.len() - 1;
+        typo[last] = if typo[last] == b'q' { b'p' } else { b'q' };
+        assert!(Address::parse(std::str::from_utf8(&typo).unwrap()).is_err());
+
+        let testnet = Address::from_public_key(wallet.public_key(), address::Network::Testnet);
+        assert!(testnet.to_string().starts_with("tnc1"));
+        assert_eq!(Address::parse(&testnet.to_string()).unwrap(), testnet);
+
+        assert!(address::validate_address("a1b2c3d4e5f6a7b8").is_err());
+        assert!(address::validate_address("bob").is_err());
+        assert!(address::validate_address("").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_wallet_addresses_are_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallets.db");
+        let wallet = Wallet::new("alice".to_string());
+        let legacy_address = hex::encode(&wallet.public_key().to_bytes()[..8]);
+
+        let storage = BlockchainStorage::create_file(&path).await.unwrap();
+        storage.save_wallet(&wallet, "pw").await.unwrap();
+        storage.close().await;
+
+        let pool = sqlx::SqlitePool::connect(&format!("{}?mode=rwc", path.display())).await.unwrap();
+        sqlx::query("UPDATE wallets SET address = ?")
+            .bind(&legacy_address)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool.close().await;
+
+        let storage = BlockchainStorage::create_file(&path).await.unwrap();
+        assert!(storage.get_wallet_info(&legacy_address).await.unwrap().is_none());
+        let loaded = storage.load_wallet(&wallet.address(), "pw").await.unwrap().unwrap();
+        assert_eq!(loaded.address(), wallet.address());
+    }
+
+    #[test]
+    fn test_multisig_transaction_verification() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let signers: Vec<Wallet> = (0..3).map(|i| Wallet::new(format!("signer {}", i))).collect();
+        let keys: Vec<PublicKey> = signers.iter().map(|w| w.public_key().clone()).collect();
+
+        let account = MultisigAccount::new(2, keys.clone()).unwrap();
+        let reversed = MultisigAccount::new(2, keys.iter().rev().cloned().collect()).unwrap();
+        assert_eq!(account.address(), reversed.address());
+        assert!(address::is_multisig_address(&account.address()));
+        assert!(MultisigAccount::new(4, keys.clone()).is_err());
+        assert!(MultisigAccount::new(1, vec![keys[0].clone(), keys[0].clone()]).is_err());
+
+        let treasury = account.address();
+        let funding = Transaction::new("genesis".to_string(), treasury.clone(), 100.0, None).unwrap();
+        blockchain.add_block(vec![funding]).unwrap();
+
+        let mut tx = Transaction::new(treasury.clone(), signers[0].address(), 10.0, None).unwrap();
+        tx.multisig = Some(multisig::MultisigAuthorization::new(account));
+        let partial = |wallet: &Wallet, tx: &Transaction| multisig::PartialSignature {
+            public_key: wallet.public_key().clone(),
+            signature: wallet.sign_transaction(&tx.signable_data().unwrap()).unwrap(),
+        };
+
+        tx.add_multisig_signature(partial(&signers[0], &tx)).unwrap();
+        assert!(tx.add_multisig_signature(partial(&signers[0], &tx)).is_err());
+        assert!(tx.add_multisig_signature(partial(&Wallet::new("outsider".to_string()), &tx)).is_err());
+        assert!(!tx.verify_signature());
+        assert!(mempool.add_transaction(tx.clone(), &blockchain).is_err());
+        assert!(blockchain.add_block(vec![tx.clone()]).is_err());
+
+        tx.add_multisig_signature(partial(&signers[2], &tx)).unwrap();
+        assert!(tx.verify_signature());
+        mempool.add_transaction(tx.clone(), &blockchain).unwrap();
         blockchain.add_block(vec![tx]).unwrap();
+        assert_eq!(blockchain.get_balance(&treasury), 90.0);
+
+        // A single key cannot spend from the multisig address
+        let single = signed_transfer(&signers[1], &signers[1].address(), 1.0, 0.0, 1);
+        let mut forged = single.clone();
+        forged.from = treasury;
+        assert!(!forged.verify_signature());
+        assert!(blockchain.add_block(vec![forged]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_offline_signed_transaction_broadcast() {
+        use std::sync::Arc;
+        use tokio::sync::RwLock;
+
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Arc::new(BlockchainStorage::create_file(dir.path().join("node.db")).await.unwrap());
+        let mempool = Arc::new(RwLock::new(Mempool::default()));
+        // The signing key only ever exists on the "offline" side, as a keystore
+        let wallet = Wallet::new("cold".to_string());
+        let mut blockchain = Blockchain::new().unwrap();
+        blockchain.add_block(vec![funding(&wallet.address(), 10.0)]).unwrap();
+        let state = ApiState {
+            blockchain: Arc::new(RwLock::new(blockchain)),
+            storage,
+            contract_engine: Arc::new(RwLock::new(ContractEngine::new().unwrap())),
+            mempool: mempool.clone(),
+            mining_stats: Arc::new(RwLock::new(MiningStats::default())),
+            network_stats: Arc::new(RwLock::new(network::NetworkStats::default())),
+            keyring: Arc::new(RwLock::new(Keyring::default())),
+            network_events: None,
+        };
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, api::create_router(state)).await.unwrap();
+        });
+
+        let keystore = EncryptedKey::encrypt(&wallet.keypair().unwrap().to_private_key_bytes(), "pw").unwrap();
+        let recipient = Wallet::new("bob".to_string()).address();
+
+        let client = client::NodeClient::new(&url).unwrap();
+        let nonce = client.next_nonce(&wallet.address()).await.unwrap();
+        let mut tx = Transaction::new(wallet.address(), recipient, 5.0, None).unwrap();
+        tx.nonce = nonce;
+
+        let cold = Wallet::from_private_key("cold".to_string(), &keystore.decrypt("pw").unwrap()).unwrap();
+        tx.sign(&cold).unwrap();
+
+        let mut tampered = tx.clone();
+        tampered.amount = 500.0;
+        assert!(client.submit_signed_transaction(&tampered).await.is_err());
+
+        let submission = client.submit_signed_transaction(&tx).await.unwrap();
+        assert_eq!(submission.transaction.id, tx.id);
+        assert!(mempool.read().await.get_transaction(&tx.id).is_some());
+        assert_eq!(client.next_nonce(&wallet.address()).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_batch_signature_verification() {
+        let wallets: Vec<Wallet> = (0..4).map(|i| Wallet::new(format!("sender {}", i))).collect();
+        let transactions: Vec<Transaction> = wallets
+            .iter()
+            .map(|wallet| signed_transfer(wallet, &wallets[0].address(), 1.0, 0.0, 0))
+            .collect();
+
+        let messages: Vec<Vec<u8>> = transactions.iter().map(|tx| tx.signable_data().unwrap()).collect();
+        fn checks<'a>(transactions: &'a [Transaction], messages: &'a [Vec<u8>]) -> Vec<crypto::SignatureCheck<'a>> {
+            transactions
+                .iter()
+                .zip(messages)
+                .map(|(tx, message)| crypto::SignatureCheck {
+                    message,
+                    signature: tx.signature.as_ref().unwrap(),
+                    public_key: tx.from_public_key.as_ref().unwrap(),
+                })
+                .collect()
+        }
+        assert_eq!(crypto::verify_batch(&checks(&transactions, &messages)), Ok(()));
+        assert_eq!(crypto::verify_batch(&[]), Ok(()));
+
+        let mut tampered = transactions.clone();
+        tampered[2].signature = tampered[1].signature.clone();
+        assert_eq!(crypto::verify_batch(&checks(&tampered, &messages)), Err(2));
+
+        let mut blockchain = Blockchain::new().unwrap();
+        blockchain.add_block(wallets.iter().map(|wallet| funding(&wallet.address(), 1.0)).collect()).unwrap();
+        let mut forged = transactions.clone();
+        forged[3].amount = 1_000.0;
+        let error = blockchain.add_block(forged).unwrap_err().to_string();
+        assert!(error.contains(&transactions[3].id));
+
+        let mut stolen = transactions[1].clone();
+        stolen.from = wallets[0].address();
+        assert!(blockchain.add_block(vec![stolen]).is_err());
+
+        blockchain.add_block(transactions).unwrap();
         assert!(blockchain.is_chain_valid().is_ok());
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_remote_signer_keeps_key_out_of_process() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("signer.sock");
+        let keypair = KeyPair::generate();
+        let listener = std::os::unix::net::UnixListener::bind(&socket).unwrap();
+        let daemon_key: std::sync::Arc<dyn Signer> = std::sync::Arc::new(keypair.clone());
+        std::thread::spawn(move || signer::serve(listener, daemon_key));
+
+        let remote = signer::RemoteSigner::connect(&socket).unwrap();
+        assert_eq!(remote.public_key(), keypair.public_key());
+        let wallet = Wallet::with_signer("remote".to_string(), std::sync::Arc::new(remote));
+        assert!(wallet.keypair().is_none());
+
+        let tx = signed_transfer(&wallet, &Wallet::new("bob".to_string()).address(), 5.0, 0.1, 0);
+        assert!(tx.verify_signature());
+
+        let storage = BlockchainStorage::create_file(dir.path().join("wallets.db")).await.unwrap();
+        assert!(storage.save_wallet(&wallet, "pw").await.is_err());
+
+        let config = MiningConfig {
+            difficulty: 1,
+            ..Default::default()
+        };
+        let result = Miner::new(config, wallet.signer())
+            .mine_block(1, vec![tx], "0".repeat(64), String::new())
+            .unwrap();
+        assert_eq!(result.block.transactions[0].to, wallet.address());
+        assert_eq!(result.block.transactions[0].from_public_key.as_ref(), Some(keypair.public_key()));
+    }
+
+    #[test]
+    fn test_key_rotation_moves_signing_authority() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let alice = Wallet::new("alice".to_string());
+        let new_key = Wallet::new("alice rotated".to_string());
+        let bob = Wallet::new("bob".to_string()).address();
+        blockchain.add_block(vec![funding(&alice.address(), 10.0)]).unwrap();
+
+        let mut rotation = Transaction::key_rotation(alice.address(), new_key.public_key().clone()).unwrap();
+        rotation.sign(&alice).unwrap();
+
+        let mut tampered = rotation.clone();
+        tampered.kind = transaction::TransactionKind::KeyRotation {
+            new_public_key: Wallet::new("mallory".to_string()).public_key().clone(),
+        };
+        assert!(!tampered.verify_signature());
+
+        // Later transactions in the same block already need the new key
+        let stale = signed_transfer(&alice, &bob, 1.0, 0.0, 1);
+        assert!(blockchain.add_block(vec![rotation.clone(), stale]).is_err());
+
+        let mut mempool = Mempool::default();
+        mempool.add_transaction(rotation.clone(), &blockchain).unwrap();
+        blockchain.add_block(vec![rotation]).unwrap();
+        assert_eq!(blockchain.authorized_key(&alice.address()), Some(new_key.public_key()));
+
+        let old_key = signed_transfer(&alice, &bob, 1.0, 0.0, 1);
+        assert!(mempool.add_transaction(old_key.clone(), &blockchain).is_err());
+        assert!(blockchain.add_block(vec![old_key]).is_err());
+
+        // Leaving the signature off does not get around the new key
+        let mut unsigned = Transaction::new(alice.address(), bob.clone(), 1.0, None).unwrap();
+        unsigned.nonce = 1;
+        assert!(blockchain.add_block(vec![unsigned.clone()]).is_err());
+        assert!(Block::new(2, vec![unsigned.clone()], "0".repeat(64), "0".repeat(64)).is_valid().is_err());
+        unsigned.from_public_key = Some(new_key.public_key().clone());
+        assert!(blockchain.add_block(vec![unsigned]).is_err());
+
+        let mut transfer = Transaction::new(alice.address(), bob, 1.0, None).unwrap();
+        transfer.nonce = 1;
+        let transfer = sign(&new_key, transfer);
+        mempool.add_transaction(transfer.clone(), &blockchain).unwrap();
+        blockchain.add_block(vec![transfer]).unwrap();
+        assert!(blockchain.is_chain_valid().is_ok());
+    }
+
+    #[test]
+    fn test_contract_transactions_execute_on_chain() {
+        use transaction::TransactionKind;
+
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let mut engine = ContractEngine::new().unwrap();
+        let owner = Wallet::new("owner".to_string());
+
+        let code = wat::parse_str(r#"(module (func (export "get") (result i32) i32.const 7))"#).unwrap();
+        let get = abi(&[("get", &[], &[contracts::ParamType::I32], false)]);
+        let deploy = Transaction::contract_deploy(owner.address(), "Storage".to_string(), code, get, 100_000).unwrap();
+        let deploy = sign(&owner, deploy);
+        let contract_id = deploy.to.clone();
+        assert_eq!(contract_id, address::contract_address(&owner.address(), 0));
+
+        let mut call = Transaction::contract_call(owner.address(), contract_id.clone(), "get".to_string(), vec![], 0.0, 1_000).unwrap();
+        call.nonce = 1;
+        let call = sign(&owner, call);
+
+        // The kind is signed: retargeting the call breaks the signature
+        let mut retargeted = call.clone();
+        retargeted.kind = TransactionKind::ContractCall {
+            function: "set".to_string(),
+            args: vec![],
+            gas_limit: 1_000,
+        };
+        assert!(!retargeted.verify_signature());
+
+        let mut fake_coinbase = Transaction::coinbase(owner.address(), 50.0).unwrap();
+        fake_coinbase.from = owner.address();
+        assert!(fake_coinbase.check_kind().is_err());
+
+        mempool.add_transaction(deploy.clone(), &blockchain).unwrap();
+        mempool.add_transaction(call.clone(), &blockchain).unwrap();
+        assert!(engine.get_contract(&contract_id).is_none());
+
+        blockchain.add_block(vec![deploy, call]).unwrap();
+        let block = blockchain.get_latest_block().unwrap().clone();
+        mempool.remove_confirmed(&block);
+        assert!(mempool.is_empty());
+
+        let receipts = engine.apply_block(&block);
+        assert_eq!(receipts.len(), 2);
+        assert!(receipts.iter().all(|receipt| receipt.result.success));
+        assert_eq!(receipts[1].result.return_value, Some(contracts::ContractValue::I32(7)));
+        assert_eq!(engine.get_contract(&contract_id).unwrap().owner, owner.address());
+    }
+
+    #[test]
+    fn test_time_and_height_locks() {
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let alice = Wallet::new("alice".to_string());
+        let bob = Wallet::new("bob".to_string()).address();
+        let now = chrono::Utc::now();
+        blockchain.add_block(vec![funding(&alice.address(), 10.0)]).unwrap();
+
+        let locked = |nonce, configure: &dyn Fn(&mut Transaction)| {
+            let mut tx = Transaction::new(alice.address(), bob.clone(), 1.0, None).unwrap();
+            tx.nonce = nonce;
+            configure(&mut tx);
+            sign(&alice, tx)
+        };
+
+        let expiring = locked(0, &|tx| tx.expires_at_height = Some(4));
+        let height_locked = locked(1, &|tx| tx.valid_after_height = Some(2));
+        let time_locked = locked(2, &|tx| tx.valid_after_time = Some(now + chrono::Duration::hours(1)));
+
+        let mut tampered = height_locked.clone();
+        tampered.valid_after_height = None;
+        assert!(!tampered.verify_signature());
+
+        assert!(mempool.add_transaction(locked(3, &|tx| tx.expires_at_height = Some(1)), &blockchain).is_err());
+        let never_valid = locked(3, &|tx| {
+            tx.valid_after_height = Some(5);
+            tx.expires_at_height = Some(6);
+        });
+        assert!(mempool.add_transaction(never_valid, &blockchain).is_err());
+
+        // Locked transactions are held, but not offered for the next block
+        for tx in [&height_locked, &time_locked, &expiring] {
+            mempool.add_transaction(tx.clone(), &blockchain).unwrap();
+        }
+        let ready: Vec<&str> = mempool.ready_transactions(&blockchain, now).iter().map(|tx| tx.id.as_str()).collect();
+        assert_eq!(ready, vec![expiring.id.as_str()]);
+
+        assert!(blockchain.add_block(vec![height_locked.clone()]).is_err());
+        assert!(blockchain.add_block(vec![time_locked.clone()]).is_err());
+        blockchain.add_block(vec![expiring.clone()]).unwrap();
+        blockchain.add_block(vec![height_locked]).unwrap();
+        assert!(blockchain.add_block(vec![expiring]).is_err());
+
+        assert_eq!(mempool.remove_expired(4, now), 1);
+        assert_eq!(mempool.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_batch_transfer_pays_every_output() {
+        use crate::transaction::TransferOutput;
+
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let employer = Wallet::new("employer".to_string());
+        let staff: Vec<String> = (0..3).map(|i| Wallet::new(format!("staff{}", i)).address()).collect();
+
+        let funding = Transaction::new("genesis".to_string(), employer.address(), 100.0, None).unwrap();
+        blockchain.add_block(vec![funding]).unwrap();
+
+        let payroll = |amount: f64, nonce| {
+            let outputs = staff.iter().map(|to| TransferOutput { to: to.clone(), amount }).collect();
+            let mut tx = Transaction::batch_transfer(employer.address(), outputs, None).unwrap();
+            tx.fee = 1.0;
+            tx.nonce = nonce;
+            sign(&employer, tx)
+        };
+
+        assert!(Transaction::batch_transfer(employer.address(), vec![], None).is_err());
+        let mut tampered = payroll(10.0, 0);
+        tampered.amount = 1.0;
+        assert!(tampered.check_kind().is_err());
+
+        // Underfunded batches are rejected as a whole
+        assert!(mempool.add_transaction(payroll(40.0, 0), &blockchain).is_err());
+        assert!(blockchain.add_block(vec![payroll(40.0, 0)]).is_err());
+        // So is a plain transfer of the same total
+        let overdraft = signed_transfer(&employer, &staff[0], 120.0, 1.0, 0);
+        assert!(mempool.add_transaction(overdraft.clone(), &blockchain).is_err());
+        assert!(blockchain.add_block(vec![overdraft]).is_err());
+
+        let batch = payroll(20.0, 0);
+        mempool.add_transaction(batch.clone(), &blockchain).unwrap();
+        assert_eq!(mempool.projected_balance(&staff[1], &blockchain), 20.0);
+        assert_eq!(mempool.transactions_for_address(&staff[2]).1.len(), 1);
+
+        blockchain.add_block(vec![batch.clone()]).unwrap();
+        assert_eq!(blockchain.get_balance(&employer.address()), 39.0);
+        assert!(staff.iter().all(|to| blockchain.get_balance(to) == 20.0));
+
+        storage.save_block(blockchain.get_latest_block().unwrap()).await.unwrap();
+        for address in [&staff[0], &staff[2], &employer.address()] {
+            let history = storage.load_address_transactions(address).await.unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].id, batch.id);
+        }
+    }
+
+    #[test]
+    fn test_block_selection_skips_unfunded_batches() {
+        use crate::transaction::TransferOutput;
+
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut mempool = Mempool::default();
+        let alice = Wallet::new("alice".to_string());
+        let bob = Wallet::new("bob".to_string());
+        let carol = Wallet::new("carol".to_string()).address();
+
+        let funding = Transaction::new("genesis".to_string(), alice.address(), 100.0, None).unwrap();
+        blockchain.add_block(vec![funding]).unwrap();
+
+        let batch = |wallet: &Wallet, amount: f64, nonce| {
+            let outputs = vec![TransferOutput { to: carol.clone(), amount }; 2];
+            let mut tx = Transaction::batch_transfer(wallet.address(), outputs, None).unwrap();
+            tx.fee = 1.0;
+            tx.nonce = nonce;
+            sign(wallet, tx)
+        };
+
+        // Pending incoming funds may be mined after the batch, so they don't count
+        mempool.add_transaction(signed_transfer(&alice, &bob.address(), 10.0, 1.0, 0), &blockchain).unwrap();
+        assert!(mempool.add_transaction(batch(&bob, 4.0, 0), &blockchain).is_err());
+
+        // Alice's batch is funded until she replaces the transfer before it
+        let payroll = batch(&alice, 40.0, 1);
+        mempool.add_transaction(payroll.clone(), &blockchain).unwrap();
+        let replacement = signed_transfer(&alice, &bob.address(), 50.0, 2.0, 0);
+        mempool.add_transaction(replacement.clone(), &blockchain).unwrap();
+
+        let ready = mempool.ready_transactions(&blockchain, chrono::Utc::now());
+        let (selected, rejected) = blockchain.select_transactions(ready);
+        assert_eq!(selected.iter().map(|tx| tx.id.as_str()).collect::<Vec<_>>(), vec![replacement.id.as_str()]);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0, payroll.id);
+
+        blockchain.add_block(selected).unwrap();
+        assert_eq!(blockchain.get_balance(&alice.address()), 48.0);
+    }
+
+    #[test]
+    fn test_contract_execution_meters_gas_and_reports_traps() {
+        use contracts::{ContractCall, ContractValue, ParamType::I64};
+
+        let mut engine = ContractEngine::new().unwrap();
+        let code = wat::parse_str(
+            r#"(module
+                (func (export "add") (param i64 i64) (result i64)
+                    local.get 0
+                    local.get 1
+                    i64.add)
+                (func (export "spin") (loop br 0))
+                (func (export "fail") unreachable))"#,
+        )
+        .unwrap();
+        let math = abi(&[("add", &[I64, I64], &[I64], false), ("spin", &[], &[], false), ("fail", &[], &[], false)]);
+        let contract = SmartContract::new("math".to_string(), "Math".to_string(), code, math, "owner".to_string(), 10_000);
+        engine.deploy_contract(contract).unwrap();
+
+        let call = |function: &str, args: Vec<ContractValue>| ContractCall {
+            contract_id: "math".to_string(),
+            function_name: function.to_string(),
+            args,
+            caller: "owner".to_string(),
+            value: 0.0,
+            gas_limit: 10_000,
+        };
+
+        let sum = engine.call_contract(call("add", vec![ContractValue::I64(40), ContractValue::I64(2)])).unwrap();
+        assert!(sum.success);
+        assert_eq!(sum.return_value, Some(ContractValue::I64(42)));
+        assert!(sum.gas_used > 0 && sum.gas_used < 100);
+
+        let spin = engine.call_contract(call("spin", vec![])).unwrap();
+        assert!(!spin.success);
+        assert_eq!(spin.gas_used, 10_000);
+        assert_eq!(spin.error.as_deref(), Some("Out of gas"));
+
+        let trap = engine.call_contract(call("fail", vec![])).unwrap();
+        assert!(trap.error.unwrap().contains("unreachable"));
+
+        assert!(!engine.call_contract(call("add", vec![ContractValue::I64(1)])).unwrap().success);
+        assert!(!engine.call_contract(call("missing", vec![])).unwrap().success);
+        assert!(engine.call_contract(ContractCall { gas_limit: 20_000, ..call("add", vec![]) }).is_err());
+    }
+
+    #[test]
+    fn test_contract_host_functions() {
+        use contracts::{BlockContext, ContractABI, ContractCall, ContractValue};
+
+        let mut engine = ContractEngine::new().unwrap();
+        let code = counter_wasm();
+        let contract = SmartContract::new("counter".to_string(), "Counter".to_string(), code, ContractABI::default(), "owner".to_string(), 100_000);
+        engine.deploy_contract(contract).unwrap();
+        engine.set_block_context(BlockContext { index: 7, timestamp: 0 });
+
+        let caller = Wallet::new("caller".to_string()).address();
+        let mut call = |function: &str| {
+            engine.call_contract(ContractCall {
+                contract_id: "counter".to_string(),
+                function_name: function.to_string(),
+                args: vec![],
+                caller: caller.clone(),
+                value: 0.0,
+                gas_limit: 100_000,
+            })
+            .unwrap()
+        };
+
+        assert_eq!(call("increment").return_value, Some(ContractValue::I64(1)));
+        let second = call("increment");
+        assert_eq!(second.return_value, Some(ContractValue::I64(2)));
+        assert_eq!(second.events[0].name, "Incremented");
+        assert!(second.gas_used > contracts::STORAGE_WRITE_GAS);
+
+        // Writes from a failed call are discarded
+        assert!(!call("increment_then_fail").success);
+        assert_eq!(call("increment").return_value, Some(ContractValue::I64(3)));
+
+        assert_eq!(call("caller_len").return_value, Some(ContractValue::I32(caller.len() as i32)));
+        assert_eq!(call("height").return_value, Some(ContractValue::I64(7)));
+        assert!(call("bad_log").error.unwrap().contains("out of bounds"));
+        assert_eq!(call("memory").error.as_deref(), Some("Invalid transaction: 'memory' is not in the contract's ABI"));
+
+        // Only payable functions accept value; a refused call keeps none of it
+        let mut pay = |function: &str| {
+            engine.call_contract(ContractCall {
+                contract_id: "counter".to_string(),
+                function_name: function.to_string(),
+                args: vec![],
+                caller: caller.clone(),
+                value: 1.0,
+                gas_limit: 100_000,
+            })
+            .unwrap()
+        };
+        assert_eq!(pay("height").error.as_deref(), Some("Invalid transaction: 'height' is not payable"));
+        assert!(pay("increment").success);
+
+        let state = engine.get_contract_state("counter").unwrap();
+        assert_eq!(state.storage.get("count"), Some(&ContractValue::Bytes(4u64.to_le_bytes().to_vec())));
+        assert_eq!(state.balance, 1.0);
+    }
+
+    #[test]
+    fn test_contract_deployment_uses_bytecode_and_abi() {
+        use contracts::{ContractABI, FunctionSignature, ParamType};
+
+        let owner = Wallet::new("owner".to_string()).address();
+        let mut deploy = Transaction::contract_deploy(owner.clone(), "Adder".to_string(), vec![1], ContractABI::default(), 100_000).unwrap();
+        deploy.set_nonce(5);
+        assert_eq!(deploy.to, address::contract_address(&owner, 5));
+        assert!(address::Address::parse(&deploy.to).unwrap().is_contract());
+        assert_ne!(deploy.to, address::contract_address(&owner, 6));
+        deploy.nonce = 6;
+        assert!(deploy.check_kind().is_err());
+
+        let function = |name: &str, inputs: Vec<ParamType>| FunctionSignature {
+            name: name.to_string(),
+            inputs,
+            outputs: vec![ParamType::U64],
+            payable: false,
+            gas_cost: 0,
+        };
+        let abi = ContractABI {
+            functions: vec![function("add", vec![ParamType::U64, ParamType::U64])],
+            events: vec![],
+        };
+        let code = wat::parse_str(format!(
+            r#"(module
+                (@custom "abi" "{}")
+                (func (export "add") (param i64 i64) (result i64) local.get 0 local.get 1 i64.add))"#,
+            serde_json::to_string(&abi).unwrap().replace('"', "\\\"")
+        ))
+        .unwrap();
+
+        let mut engine = ContractEngine::new().unwrap();
+        assert_eq!(engine.check_deployment(&code, ContractABI::default()).unwrap(), abi);
+
+        let wrong = ContractABI {
+            functions: vec![function("add", vec![ParamType::I32]), function("sub", vec![])],
+            events: vec![],
+        };
+        let error = engine.check_deployment(&code, wrong).unwrap_err().to_string();
+        assert!(error.contains("'add' is exported as") && error.contains("'sub' is not exported"));
+
+        let contract = SmartContract::new(deploy.to.clone(), "Adder".to_string(), code, ContractABI::default(), owner, 100_000);
+        engine.deploy_contract(contract.clone()).unwrap();
+        assert_eq!(engine.get_contract(&deploy.to).unwrap().abi, abi);
+        assert!(engine.deploy_contract(contract).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_contract_state_survives_restart() {
+        use contracts::{ContractABI, ContractCall, ContractValue};
+
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        let blockchain = Blockchain::new().unwrap();
+        let block = blockchain.get_latest_block().unwrap();
+
+        let increment = |engine: &mut ContractEngine| {
+            engine
+                .call_contract(ContractCall {
+                    contract_id: "counter".to_string(),
+                    function_name: "increment".to_string(),
+                    args: vec![],
+                    caller: "caller".to_string(),
+                    value: 2.5,
+                    gas_limit: 100_000,
+                })
+                .unwrap()
+                .return_value
+        };
+
+        let mut engine = ContractEngine::new().unwrap();
+        let contract = SmartContract::new("counter".to_string(), "Counter".to_string(), counter_wasm(), ContractABI::default(), "owner".to_string(), 100_000);
+        engine.deploy_contract(contract).unwrap();
+        increment(&mut engine);
+        increment(&mut engine);
+
+        let changes = engine.take_changes();
+        assert_eq!(changes.deployed.len(), 1);
+        assert!(engine.take_changes().is_empty());
+        storage.save_block_with_contracts(block, &[], &changes).await.unwrap();
+
+        let mut restarted = ContractEngine::new().unwrap();
+        for (contract, state) in storage.load_contracts().await.unwrap() {
+            restarted.restore_contract(contract, state).unwrap();
+        }
+        assert_eq!(restarted.get_contract_state("counter").unwrap().balance, 5.0);
+        assert_eq!(increment(&mut restarted), Some(ContractValue::I64(3)));
+        assert!(restarted.take_changes().deployed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_chain_survives_restart() {
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        let mut blockchain = Blockchain::new().unwrap();
+        let alice = Wallet::new("alice".to_string());
+        let bob = Wallet::new("bob".to_string()).address();
+
+        let funding = Transaction::new("genesis".to_string(), alice.address(), 100.0, None).unwrap();
+        blockchain.add_block(vec![funding]).unwrap();
+        // A rejected block must not leave a gap in the PoH sequence
+        assert!(blockchain.add_block(vec![signed_transfer(&alice, &bob, 10.0, 0.1, 1)]).is_err());
+        blockchain.add_block(vec![signed_transfer(&alice, &bob, 10.0, 0.1, 0)]).unwrap();
+        for block in blockchain.chain() {
+            storage.save_block(block).await.unwrap();
+        }
+
+        let mut restored = Blockchain::from_blocks(storage.load_all_blocks().await.unwrap()).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored.next_nonce(&alice.address()), 1);
+        assert_eq!(restored.get_balance(&bob), 10.0);
+        assert_eq!(restored.poh_tick_count(), blockchain.poh_tick_count());
+        restored.add_block(vec![signed_transfer(&alice, &bob, 10.0, 0.1, 1)]).unwrap();
+
+        let mut blocks = storage.load_all_blocks().await.unwrap();
+        blocks.remove(1);
+        assert!(Blockchain::from_blocks(blocks.clone()).is_err());
+        assert!(Blockchain::from_blocks(blocks.split_off(1)).is_err());
+    }
+
+    #[test]
+    fn test_contract_arguments_follow_abi() {
+        use contracts::{ContractABI, ContractCall, ContractValue, FunctionSignature, ParamType};
+        use serde_json::json;
+
+        let code = wat::parse_str(
+            r#"(module
+                (memory (export "memory") 1)
+                (global $next (mut i32) (i32.const 1024))
+                (func (export "alloc") (param $len i32) (result i32)
+                    (global.get $next)
+                    (global.set $next (i32.add (global.get $next) (local.get $len))))
+                (func $echo (export "echo") (param $ptr i32) (param $len i32) (result i64)
+                    (i64.or
+                        (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+                        (i64.extend_i32_u (local.get $len))))
+                (export "echo_address" (func $echo))
+                (func (export "len") (param i32 i32) (result i32) (local.get 1))
+                (func (export "add") (param i64 i64) (result i64) (i64.add (local.get 0) (local.get 1))))"#,
+        )
+        .unwrap();
+        let function = |name: &str, inputs: Vec<ParamType>, output: ParamType| FunctionSignature {
+            name: name.to_string(),
+            inputs,
+            outputs: vec![output],
+            payable: false,
+            gas_cost: 0,
+        };
+        let abi = ContractABI {
+            functions: vec![
+                function("echo", vec![ParamType::String], ParamType::String),
+                function("echo_address", vec![ParamType::Address], ParamType::Address),
+                function("len", vec![ParamType::Bytes], ParamType::U32),
+                function("add", vec![ParamType::U64, ParamType::U64], ParamType::U64),
+            ],
+            events: vec![],
+        };
+
+        let mut engine = ContractEngine::new().unwrap();
+        let contract = SmartContract::new("codec".to_string(), "Codec".to_string(), code, abi.clone(), "owner".to_string(), 100_000);
+        engine.deploy_contract(contract).unwrap();
+
+        let mut call = |function: &str, args: serde_json::Value| {
+            let args = abi.function(function).unwrap().parse_args(args.as_array().unwrap())?;
+            let result = engine
+                .call_contract(ContractCall {
+                    contract_id: "codec".to_string(),
+                    function_name: function.to_string(),
+                    args,
+                    caller: "caller".to_string(),
+                    value: 0.0,
+                    gas_limit: 100_000,
+                })
+                .unwrap();
+            Ok::<_, errors::BlockchainError>(result)
+        };
+
+        let address = Wallet::new("holder".to_string()).address();
+        assert_eq!(call("echo", json!(["héllo"])).unwrap().return_value, Some(ContractValue::String("héllo".to_string())));
+        assert_eq!(call("echo_address", json!([address])).unwrap().return_value, Some(ContractValue::Address(address)));
+        assert_eq!(call("len", json!(["0xdeadbeef"])).unwrap().return_value, Some(ContractValue::U32(4)));
+        assert_eq!(call("add", json!(["18446744073709551000", 15])).unwrap().return_value, Some(ContractValue::U64(18446744073709551015)));
+
+        let error = call("add", json!(["x", 1])).unwrap_err().to_string();
+        assert!(error.contains("Argument 1 of 'add'") && error.contains("U64"));
+        assert!(call("add", json!([-1, 1])).unwrap_err().to_string().contains("out of range"));
+        assert!(call("add", json!([1])).unwrap_err().to_string().contains("takes 2 arguments"));
+        assert!(call("len", json!(["zz"])).is_err());
+        assert!(call("echo_address", json!(["not-an-address"])).is_err());
+
+        // Values that bypass JSON parsing are still checked against the ABI
+        let result = engine
+            .call_contract(ContractCall {
+                contract_id: "codec".to_string(),
+                function_name: "add".to_string(),
+                args: vec![ContractValue::I64(1), ContractValue::I64(2)],
+                caller: "caller".to_string(),
+                value: 0.0,
+                gas_limit: 100_000,
+            })
+            .unwrap();
+        assert!(result.error.unwrap().contains("the ABI expects U64"));
+    }
+
+    #[test]
+    fn test_contract_views_do_not_mutate_state() {
+        use crate::config::ContractsConfig;
+        use contracts::{ContractABI, ContractCall, ContractValue};
+
+        let config = ContractsConfig {
+            max_gas_limit: 50_000,
+            ..Default::default()
+        };
+        let mut engine = ContractEngine::with_config(config).unwrap();
+        let contract = |gas_limit| SmartContract::new("counter".to_string(), "Counter".to_string(), counter_wasm(), ContractABI::default(), "owner".to_string(), gas_limit);
+        assert!(engine.deploy_contract(contract(50_001)).is_err());
+        engine.deploy_contract(contract(50_000)).unwrap();
+
+        let call = |gas_limit, value| ContractCall {
+            contract_id: "counter".to_string(),
+            function_name: "increment".to_string(),
+            args: vec![],
+            caller: "caller".to_string(),
+            value,
+            gas_limit,
+        };
+        engine.call_contract(call(50_000, 0.0)).unwrap();
+        engine.take_changes();
+
+        // The view sees current state, but its write is thrown away
+        assert_eq!(engine.view(call(50_000, 0.0)).unwrap().return_value, Some(ContractValue::I64(2)));
+        assert_eq!(engine.view(call(50_000, 0.0)).unwrap().return_value, Some(ContractValue::I64(2)));
+        assert!(engine.take_changes().is_empty());
+
+        assert!(engine.view(call(50_001, 0.0)).is_err());
+        assert!(engine.view(call(50_000, 1.0)).is_err());
+        assert_eq!(engine.view(call(200, 0.0)).unwrap().error.as_deref(), Some("Out of gas"));
+        assert_eq!(engine.call_contract(call(50_000, 0.0)).unwrap().return_value, Some(ContractValue::I64(2)));
+
+        // A contract stored with a higher limit is still capped at the maximum
+        let stored = SmartContract {
+            gas_limit: 100_000,
+            ..engine.get_contract("counter").unwrap().clone()
+        };
+        let mut engine = ContractEngine::with_config(ContractsConfig {
+            max_gas_limit: 50_000,
+            ..Default::default()
+        })
+        .unwrap();
+        let state = contracts::ContractState {
+            storage: Default::default(),
+            balance: 0.0,
+        };
+        engine.restore_contract(stored, state).unwrap();
+        assert!(engine.call_contract(call(50_001, 0.0)).is_err());
+        assert!(engine.call_contract(call(50_000, 0.0)).unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_failed_contract_calls_are_reverted() {
+        use contracts::{ContractABI, ContractValue};
+
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut engine = ContractEngine::new().unwrap();
+        let owner = Wallet::new("owner".to_string());
+        let contract_id = address::contract_address(&owner.address(), 0);
+        let counter = |id: &str| SmartContract::new(id.to_string(), "Counter".to_string(), counter_wasm(), ContractABI::default(), owner.address(), 100_000);
+        engine.deploy_contract(counter(&contract_id)).unwrap();
+        engine.take_changes();
+
+        let call = |function: &str, value, nonce| {
+            let mut tx = Transaction::contract_call(owner.address(), contract_id.clone(), function.to_string(), vec![], value, 50_000).unwrap();
+            tx.nonce = nonce;
+            tx.fee = 0.5;
+            sign(&owner, tx)
+        };
+        let coinbase = Transaction::coinbase(owner.address(), 100.0).unwrap();
+        blockchain.add_block(vec![coinbase, call("increment", 5.0, 0), call("increment_then_fail", 3.0, 1)]).unwrap();
+        let block = blockchain.get_latest_block().unwrap().clone();
+        let receipts = engine.apply_block(&block);
+        assert!(receipts[0].result.success);
+        assert!(!receipts[1].result.success);
+        blockchain.record_contract_receipts(&receipts);
+
+        // The failed call's write and value are dropped; only its fee is paid
+        let count = |engine: &ContractEngine| engine.get_contract_state(&contract_id).unwrap().storage.get("count").cloned();
+        let balance = |engine: &ContractEngine| engine.get_contract_state(&contract_id).unwrap().balance;
+        assert_eq!(count(&engine), Some(ContractValue::Bytes(1u64.to_le_bytes().to_vec())));
+        assert_eq!(balance(&engine), 5.0);
+        assert_eq!(blockchain.get_balance(&contract_id), 5.0);
+        assert_eq!(blockchain.get_balance(&owner.address()), 94.0);
+
+        // The failed call stays reverted when the chain is reloaded
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        storage.save_block(blockchain.get_block(0).unwrap()).await.unwrap();
+        storage.save_block_with_contracts(&block, &receipts, &Default::default()).await.unwrap();
+        let mut restored = Blockchain::from_blocks(storage.load_all_blocks().await.unwrap()).unwrap();
+        restored.record_contract_receipts(&storage.load_contract_receipts().await.unwrap());
+        assert_eq!(restored.get_balance(&contract_id), 5.0);
+        assert_eq!(restored.get_balance(&owner.address()), 94.0);
+        assert_eq!(engine.take_changes().balances.get(&contract_id), Some(&5.0));
+
+        // Reverting a block restores the state committed before it
+        assert!(engine.apply_transaction(&call("increment", 2.0, 2)).unwrap().success);
+        engine.deploy_contract(counter("other")).unwrap();
+        assert_eq!(balance(&engine), 7.0);
+        engine.revert_changes();
+        assert!(engine.take_changes().is_empty());
+        assert!(engine.get_contract("other").is_none());
+        assert_eq!(count(&engine), Some(ContractValue::Bytes(1u64.to_le_bytes().to_vec())));
+        assert_eq!(balance(&engine), 5.0);
+    }
+
+    #[tokio::test]
+    async fn test_block_is_committed_only_once_persisted() {
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        let mut blockchain = Blockchain::new().unwrap();
+        let mut engine = ContractEngine::new().unwrap();
+        let owner = Wallet::new("owner".to_string());
+
+        let code = wat::parse_str(r#"(module (func (export "get") (result i32) i32.const 7))"#).unwrap();
+        let deploy = sign(&owner, Transaction::contract_deploy(owner.address(), "Storage".to_string(), code, Default::default(), 100_000).unwrap());
+        let contract_id = deploy.to.clone();
+
+        let prepared = blockchain.prepare_block(vec![deploy]).unwrap();
+        assert_eq!(blockchain.len(), 1);
+        engine.apply_block(prepared.block());
+        assert_eq!(engine.changes().deployed.len(), 1);
+
+        // A block already stored at this height makes the save fail
+        let mut conflicting = prepared.block().clone();
+        conflicting.hash = "conflict".to_string();
+        storage.save_block(&conflicting).await.unwrap();
+        assert!(storage.save_block_with_contracts(prepared.block(), &[], engine.changes()).await.is_err());
+        engine.revert_changes();
+        assert!(engine.get_contract(&contract_id).is_none());
+        assert_eq!(blockchain.next_nonce(&owner.address()), 0);
+
+        let storage = BlockchainStorage::create_in_memory().await.unwrap();
+        engine.apply_block(prepared.block());
+        storage.save_block_with_contracts(prepared.block(), &[], engine.changes()).await.unwrap();
+        engine.take_changes();
+        blockchain.commit_block(prepared);
+        assert_eq!(blockchain.len(), 2);
+        assert!(engine.get_contract(&contract_id).is_some());
+        assert_eq!(storage.load_contracts().await.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_contract_to_contract_calls() {
+        use crate::config::ContractsConfig;
+        use contracts::{ContractABI, ContractCall, ContractReceipt, ContractValue, ParamType, ValueTransfer};
+
+        let proxy = wat::parse_str(
+            r#"(module
+                (import "nchain" "call" (func $call (param i32 i32 i32 i32 i32 i32 f64 i64 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "counter")
+                (data (i32.const 16) "increment")
+                (data (i32.const 32) "increment_then_fail")
+                (data (i32.const 64) "proxy")
+                (data (i32.const 80) "recurse")
+                (data (i32.const 96) "height")
+                (func $increment (param f64 i64) (result i32)
+                    (call $call (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 9) (i32.const 0) (i32.const 0)
+                        (local.get 0) (local.get 1) (i32.const 128) (i32.const 8)))
+                (func (export "forward") (result i64)
+                    (drop (call $increment (f64.const 0) (i64.const 0)))
+                    (i64.load (i32.const 128)))
+                (func (export "forward_fail") (result i32)
+                    (drop (call $increment (f64.const 0) (i64.const 0)))
+                    (call $call (i32.const 0) (i32.const 7) (i32.const 32) (i32.const 19) (i32.const 0) (i32.const 0)
+                        (f64.const 0) (i64.const 0) (i32.const 128) (i32.const 8)))
+                (func (export "pay") (param f64) (result i32) (call $increment (local.get 0) (i64.const 0)))
+                (func (export "pay_height") (param f64) (result i32)
+                    (call $call (i32.const 0) (i32.const 7) (i32.const 96) (i32.const 6) (i32.const 0) (i32.const 0)
+                        (local.get 0) (i64.const 0) (i32.const 128) (i32.const 8)))
+                (func (export "stingy") (result i32) (call $increment (f64.const 0) (i64.const 500)))
+                (func (export "recurse") (result i32)
+                    (call $call (i32.const 64) (i32.const 5) (i32.const 80) (i32.const 7) (i32.const 0) (i32.const 0)
+                        (f64.const 0) (i64.const 0) (i32.const 128) (i32.const 8))))"#,
+        )
+        .unwrap();
+
+        let proxy_abi = abi(&[
+            ("forward", &[], &[ParamType::I64], false),
+            ("forward_fail", &[], &[ParamType::I32], false),
+            ("pay", &[ParamType::F64], &[ParamType::I32], true),
+            ("pay_height", &[ParamType::F64], &[ParamType::I32], false),
+            ("stingy", &[], &[ParamType::I32], false),
+            ("recurse", &[], &[ParamType::I32], false),
+        ]);
+        let engine_with = |allow_reentrancy| {
+            let config = ContractsConfig {
+                max_call_depth: 3,
+                allow_reentrancy,
+                ..Default::default()
+            };
+            let mut engine = ContractEngine::with_config(config).unwrap();
+            for (id, code, abi) in [("counter", counter_wasm(), ContractABI::default()), ("proxy", proxy.clone(), proxy_abi.clone())] {
+                let contract = SmartContract::new(id.to_string(), id.to_string(), code, abi, "owner".to_string(), 100_000);
+                engine.deploy_contract(contract).unwrap();
+            }
+            engine
+        };
+        let call = |function: &str, args, value| ContractCall {
+            contract_id: "proxy".to_string(),
+            function_name: function.to_string(),
+            args,
+            caller: "alice".to_string(),
+            value,
+            gas_limit: 100_000,
+        };
+        let mut engine = engine_with(false);
+        let count = |engine: &ContractEngine| engine.get_contract_state("counter").unwrap().storage.get("count").cloned();
+        let balance = |engine: &ContractEngine, id| engine.get_contract_state(id).unwrap().balance;
+
+        let result = engine.call_contract(call("forward", vec![], 0.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I64(1)));
+        assert_eq!(result.events.len(), 1);
+
+        // A failed callee is reverted without failing its caller
+        let result = engine.call_contract(call("forward_fail", vec![], 0.0)).unwrap();
+        assert!(result.success);
+        assert_eq!(result.return_value, Some(ContractValue::I32(-1)));
+        assert_eq!(result.events.len(), 1);
+        assert!(result.logs[0].starts_with("Call to counter::increment_then_fail failed: Contract trapped"));
+        assert_eq!(count(&engine), Some(ContractValue::Bytes(2u64.to_le_bytes().to_vec())));
+
+        // Forwarded gas caps the callee, and only what it used is charged
+        let result = engine.call_contract(call("stingy", vec![], 0.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(-1)));
+        assert_eq!(result.logs, vec!["Call to counter::increment failed: Out of gas".to_string()]);
+        assert!(result.gas_used > 500 && result.gas_used < 1_000);
+
+        // Value moves from the calling contract's balance
+        let result = engine.call_contract(call("pay", vec![ContractValue::F64(2.0)], 5.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(8)));
+        assert_eq!((balance(&engine, "proxy"), balance(&engine, "counter")), (3.0, 2.0));
+        let transfer = ValueTransfer {
+            from: "proxy".to_string(),
+            to: "counter".to_string(),
+            amount: 2.0,
+        };
+        assert_eq!(result.transfers, vec![transfer]);
+        // The ledger sees the transfer once the receipt is recorded
+        let mut blockchain = Blockchain::new().unwrap();
+        blockchain.record_contract_receipts(&[ContractReceipt {
+            transaction_id: "pay".to_string(),
+            result,
+        }]);
+        assert_eq!((blockchain.get_balance("proxy"), blockchain.get_balance("counter")), (-2.0, 2.0));
+        let result = engine.call_contract(call("pay", vec![ContractValue::F64(10.0)], 0.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(-1)));
+        assert!(result.transfers.is_empty());
+        assert_eq!((balance(&engine, "proxy"), balance(&engine, "counter")), (3.0, 2.0));
+
+        // Value sent to a function that is not payable is refused
+        let result = engine.call_contract(call("pay_height", vec![ContractValue::F64(1.0)], 0.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(-1)));
+        assert_eq!(result.logs, vec!["Call to counter::height failed: Invalid transaction: 'height' is not payable".to_string()]);
+        assert_eq!((balance(&engine, "proxy"), balance(&engine, "counter")), (3.0, 2.0));
+
+        let result = engine.call_contract(call("recurse", vec![], 0.0)).unwrap();
+        assert_eq!(result.logs, vec!["Call to proxy::recurse failed: Reentrant call to proxy".to_string()]);
+
+        let mut engine = engine_with(true);
+        // Only the innermost call fails; the others return its 4-byte result
+        let result = engine.call_contract(call("recurse", vec![], 0.0)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(4)));
+        assert_eq!(result.logs, vec!["Call to proxy::recurse failed: Maximum call depth of 3 exceeded".to_string()]);
+    }
+
+    #[test]
+    fn test_contract_time_and_memory_limits() {
+        use crate::config::ContractsConfig;
+        use contracts::{ContractCall, ContractValue, ParamType::I32};
+
+        let config = ContractsConfig {
+            max_memory_mb: 1,
+            execution_timeout_secs: 1,
+            view_timeout_secs: 1,
+            max_gas_limit: u64::MAX,
+            ..Default::default()
+        };
+        let mut engine = ContractEngine::with_config(config).unwrap();
+        let code = |wat: &str| wat::parse_str(wat).unwrap();
+        let deploy = |engine: &mut ContractEngine, id: &str, code, abi| {
+            let contract = SmartContract::new(id.to_string(), id.to_string(), code, abi, "owner".to_string(), u64::MAX);
+            engine.deploy_contract(contract).unwrap();
+        };
+        let grower = abi(&[("grow", &[I32], &[I32], false), ("grow_table", &[I32], &[I32], false)]);
+        deploy(&mut engine, "grower", code(
+            r#"(module
+                (memory (export "memory") 1)
+                (table 1 funcref)
+                (func (export "grow") (param i32) (result i32) (memory.grow (local.get 0)))
+                (func (export "grow_table") (param i32) (result i32) (table.grow (ref.null func) (local.get 0))))"#,
+        ), grower);
+        deploy(&mut engine, "spinner", code(r#"(module (func (export "spin") (loop (br 0))))"#), abi(&[("spin", &[], &[], false)]));
+        deploy(&mut engine, "counter", counter_wasm(), Default::default());
+        deploy(&mut engine, "repeater", code(
+            r#"(module
+                (import "nchain" "call" (func $call (param i32 i32 i32 i32 i32 i32 f64 i64 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "counter")
+                (data (i32.const 16) "height")
+                (func (export "repeat") (param $n i32) (result i32)
+                    (local $ok i32)
+                    (block $done
+                        (loop $next
+                            (br_if $done (i32.eqz (local.get $n)))
+                            (if (i32.ge_s (call $call (i32.const 0) (i32.const 7) (i32.const 16) (i32.const 6) (i32.const 0) (i32.const 0)
+                                    (f64.const 0) (i64.const 0) (i32.const 128) (i32.const 8)) (i32.const 0))
+                                (then (local.set $ok (i32.add (local.get $ok) (i32.const 1)))))
+                            (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                            (br $next)))
+                    (local.get $ok)))"#,
+        ), abi(&[("repeat", &[I32], &[I32], false)]));
+
+        let call = |id: &str, function: &str, args, gas_limit| ContractCall {
+            contract_id: id.to_string(),
+            function_name: function.to_string(),
+            args,
+            caller: "caller".to_string(),
+            value: 0.0,
+            gas_limit,
+        };
+
+        // 1 MB is 16 pages: growth past it fails like any failed memory.grow
+        let grow = |engine: &mut ContractEngine, pages| engine.call_contract(call("grower", "grow", vec![ContractValue::I32(pages)], 10_000)).unwrap().return_value;
+        assert_eq!(grow(&mut engine, 16), Some(ContractValue::I32(-1)));
+        assert_eq!(grow(&mut engine, 15), Some(ContractValue::I32(1)));
+        let result = engine.call_contract(call("grower", "grow_table", vec![ContractValue::I32(contracts::MAX_TABLE_ELEMENTS as i32)], 10_000)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(-1)));
+
+        // Callee instances keep their memory until the call ends, so only 15
+        // one-page callees fit next to the caller's page, returned or not
+        let result = engine.call_contract(call("repeater", "repeat", vec![ContractValue::I32(20)], 1_000_000)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(15)));
+        let result = engine.call_contract(call("repeater", "repeat", vec![ContractValue::I32(15)], 1_000_000)).unwrap();
+        assert_eq!(result.return_value, Some(ContractValue::I32(15)));
+
+        // Gas would last far longer than the one second timeout
+        let started = std::time::Instant::now();
+        let result = engine.call_contract(call("spinner", "spin", vec![], u64::MAX)).unwrap();
+        assert_eq!(result.error.as_deref(), Some("Execution timed out"));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+
+        // Views run on a snapshot under their own timeout
+        let snapshot = engine.snapshot();
+        let result = snapshot.view(call("spinner", "spin", vec![], u64::MAX)).unwrap();
+        assert_eq!(result.error.as_deref(), Some("Execution timed out"));
+        assert_eq!(snapshot.view(call("grower", "grow", vec![ContractValue::I32(16)], 10_000)).unwrap().return_value, Some(ContractValue::I32(-1)));
+    }
+
+    #[test]
+    fn test_contract_validation_reports_nondeterminism() {
+        use crate::config::{ContractsConfig, FloatOps};
+        use contracts::ContractABI;
+
+        let code = |wat: &str| wat::parse_str(wat).unwrap();
+        let mut engine = ContractEngine::new().unwrap();
+        let contract = SmartContract::new(
+            "wasi".to_string(),
+            "Wasi".to_string(),
+            code(
+                r#"(module
+                    (import "wasi_snapshot_preview1" "fd_write" (func (param i32 i32 i32 i32) (result i32)))
+                    (import "nchain" "random" (func))
+                    (memory 300)
+                    (func $init)
+                    (start $init))"#,
+            ),
+            ContractABI::default(),
+            "owner".to_string(),
+            100_000,
+        );
+
+        let error = engine.deploy_contract(contract).unwrap_err().to_string();
+        for problem in [
+            "import 'wasi_snapshot_preview1::fd_write' is not allowed",
+            "'nchain::random' is not a host function",
+            "start functions are not allowed",
+            "initial memory of 300 pages exceeds the limit",
+            "memory must be exported as 'memory'",
+        ] {
+            assert!(error.contains(problem), "{}", error);
+        }
+        assert!(engine.get_contract("wasi").is_none());
+
+        // Floats are canonicalized by default, or rejected outright
+        let half = code(r#"(module (func (export "half") (param f64) (result f64) (f64.div (local.get 0) (f64.const 2))))"#);
+        assert!(engine.check_deployment(&half, ContractABI::default()).is_ok());
+        let strict = ContractEngine::with_config(ContractsConfig {
+            float_ops: FloatOps::Reject,
+            ..Default::default()
+        })
+        .unwrap();
+        let error = strict.check_deployment(&half, ContractABI::default()).unwrap_err().to_string();
+        assert!(error.contains("floating-point types and instructions are not allowed"), "{}", error);
+        assert!(strict.check_deployment(&counter_wasm(), ContractABI::default()).is_ok());
+    }
 }
\ No newline at end of file
@@ -1,21 +1,31 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use std::collections::HashMap;
 use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
-use tracing::{info, Level};
+use tracing::{info, warn, Level};
 use tracing_subscriber;
 
 use blockchain::{
-    api::{start_server, ApiState},
+    address::validate_address,
+    api::{start_server, ApiState, WalletExport},
     cli::*,
+    client::NodeClient,
+    config::{ContractsConfig, KeystoreConfig, MempoolConfig},
     contracts::ContractEngine,
-    crypto::Wallet,
+    crypto::{PublicKey, Wallet},
+    hdwallet::{generate_mnemonic, HdWallet},
+    keystore::{EncryptedKey, Keyring},
+    mempool::Mempool,
+    multisig::{MultisigAccount, PartialSignature},
     network::{NetworkConfig, NetworkStats, P2PNode},
     storage::BlockchainStorage,
-    Blockchain,
+    transaction::TransferOutput,
+    Blockchain, BlockchainConfig,
 };
+#[cfg(unix)]
+use blockchain::signer::RemoteSigner;
 
 #[derive(Parser)]
 #[command(name = "blockchain")]
@@ -39,6 +49,8 @@ enum Commands {
         p2p_port: u16,
         #[arg(long, default_value = "blockchain.db")]
         database: String,
+        #[arg(long, help = "Unix socket of a blockchain-signer daemon holding the node's key")]
+        signer_socket: Option<String>,
     },
 
     #[command(about = "Start API server only")]
@@ -47,12 +59,154 @@ enum Commands {
         port: u16,
         #[arg(long, default_value = "blockchain.db")]
         database: String,
+        #[arg(long, help = "Unix socket of a blockchain-signer daemon holding the node's key")]
+        signer_socket: Option<String>,
     },
 
     #[command(about = "Create a new wallet")]
     CreateWallet {
         #[arg(help = "Wallet name")]
         name: String,
+        #[arg(long, help = "Password used to encrypt the private key (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Encrypt wallets stored with plaintext private keys")]
+    MigrateWallets {
+        #[arg(long, help = "Password used to encrypt the private keys (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Create an HD wallet backed by a mnemonic phrase")]
+    CreateHdWallet {
+        #[arg(help = "HD wallet name")]
+        name: String,
+        #[arg(long, default_value = "24", help = "Mnemonic length in words (12, 15, 18, 21 or 24)")]
+        words: usize,
+        #[arg(long, help = "Password used to encrypt the seed (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Derive the next address of an HD wallet")]
+    DeriveAddress {
+        #[arg(help = "HD wallet name")]
+        name: String,
+        #[arg(long, help = "HD wallet password (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Restore an HD wallet from its mnemonic phrase")]
+    RestoreHdWallet {
+        #[arg(help = "HD wallet name")]
+        name: String,
+        #[arg(long, default_value = "1", help = "Number of accounts to re-derive")]
+        accounts: u32,
+        #[arg(long, help = "Mnemonic phrase (prompted if omitted)")]
+        mnemonic: Option<String>,
+        #[arg(long, help = "Password used to encrypt the seed (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Export a wallet's encrypted keystore to a file")]
+    ExportWallet {
+        #[arg(help = "Wallet address")]
+        address: String,
+        #[arg(long, short, help = "Keystore file to write")]
+        output: String,
+        #[arg(long, help = "Wallet password (prompted if omitted)")]
+        password: Option<String>,
+    },
+
+    #[command(about = "Write an unsigned transaction to a file for offline signing")]
+    BuildTransaction {
+        #[arg(help = "From address")]
+        from: String,
+        #[arg(help = "To address")]
+        to: String,
+        #[arg(help = "Amount")]
+        amount: f64,
+        #[arg(long, default_value = "0", help = "Transaction fee")]
+        fee: f64,
+        #[arg(long, help = "Sender nonce (looked up from --node if omitted)")]
+        nonce: Option<u64>,
+        #[arg(long, help = "Optional data")]
+        data: Option<String>,
+        #[arg(long, help = "Node URL used to look up the nonce, e.g. http://127.0.0.1:8080")]
+        node: Option<String>,
+        #[arg(long, short, help = "Transaction file to write")]
+        output: String,
+    },
+
+    #[command(about = "Write an unsigned transfer to many recipients to a file for offline signing")]
+    BuildBatchTransaction {
+        #[arg(help = "From address")]
+        from: String,
+        #[arg(help = "JSON file with an array of {\"to\", \"amount\"} outputs")]
+        outputs: String,
+        #[arg(long, default_value = "0", help = "Transaction fee")]
+        fee: f64,
+        #[arg(long, help = "Sender nonce (looked up from --node if omitted)")]
+        nonce: Option<u64>,
+        #[arg(long, help = "Optional data")]
+        data: Option<String>,
+        #[arg(long, help = "Node URL used to look up the nonce, e.g. http://127.0.0.1:8080")]
+        node: Option<String>,
+        #[arg(long, short, help = "Transaction file to write")]
+        output: String,
+    },
+
+    #[command(about = "Build an unsigned transaction moving an account to a new signing key")]
+    BuildKeyRotation {
+        #[arg(help = "Account address")]
+        address: String,
+        #[arg(help = "New public key (hex)")]
+        new_public_key: String,
+        #[arg(long, default_value = "0", help = "Transaction fee")]
+        fee: f64,
+        #[arg(long, help = "Sender nonce (looked up from --node if omitted)")]
+        nonce: Option<u64>,
+        #[arg(long, help = "Node URL used to look up the nonce, e.g. http://127.0.0.1:8080")]
+        node: Option<String>,
+        #[arg(long, short, help = "Transaction file to write")]
+        output: String,
+    },
+
+    #[command(about = "Sign a transaction file with a keystore, without contacting a node")]
+    SignTransaction {
+        #[arg(help = "Transaction file")]
+        file: String,
+        #[arg(long, help = "Keystore file (from export-wallet or the export API)")]
+        keystore: String,
+        #[arg(long, help = "Keystore password (prompted if omitted)")]
+        password: Option<String>,
+        #[arg(long, short, help = "Signed transaction file to write (defaults to overwriting the input)")]
+        output: Option<String>,
+    },
+
+    #[command(about = "Submit a signed transaction file to a node")]
+    BroadcastTransaction {
+        #[arg(help = "Signed transaction file")]
+        file: String,
+        #[arg(long, default_value = "http://127.0.0.1:8080", help = "Node URL")]
+        node: String,
+    },
+
+    #[command(about = "Create an M-of-N multisig address")]
+    CreateMultisig {
+        #[arg(long, help = "Number of signatures required")]
+        threshold: usize,
+        #[arg(required = true, help = "Co-signer public keys (hex) or addresses of stored wallets")]
+        keys: Vec<String>,
+    },
+
+    #[command(about = "Add a wallet's signature to a pending multisig transaction")]
+    SignMultisig {
+        #[arg(help = "Multisig proposal ID")]
+        proposal_id: String,
+        #[arg(help = "Co-signer wallet address")]
+        wallet: String,
+        #[arg(long, help = "Wallet password (prompted if omitted)")]
+        password: Option<String>,
     },
 
     #[command(about = "Mine a block")]
@@ -127,33 +281,293 @@ async fn main() -> Result<()> {
             interactive.run()?;
         }
 
-        Some(Commands::Node { api_port, p2p_port, database }) => {
+        Some(Commands::Node { api_port, p2p_port, database, signer_socket }) => {
             info!("Starting full node with API server on port {} and P2P on port {}", api_port, p2p_port);
-            start_full_node(api_port, p2p_port, &database).await?;
+            start_full_node(api_port, p2p_port, &database, signer_socket.as_deref()).await?;
         }
 
-        Some(Commands::Api { port, database }) => {
+        Some(Commands::Api { port, database, signer_socket }) => {
             info!("Starting API server on port {}", port);
-            start_api_server(port, &database).await?;
+            start_api_server(port, &database, signer_socket.as_deref()).await?;
         }
 
-        Some(Commands::CreateWallet { name }) => {
+        Some(Commands::CreateWallet { name, password }) => {
             let storage = BlockchainStorage::create_file(&format!("{}.db", "blockchain")).await?;
+            let password = read_new_password(password)?;
             let wallet = Wallet::new(name.clone());
 
-            storage.save_wallet(&wallet).await?;
+            storage.save_wallet(&wallet, &password).await?;
 
             println!("✅ Wallet created successfully!");
             println!("Name: {}", wallet.name);
             println!("Address: {}", wallet.address());
-            println!("Public Key: {}", wallet.keypair.public_key());
+            println!("Public Key: {}", wallet.public_key());
+        }
+
+        Some(Commands::MigrateWallets { password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+
+            if !storage.has_plaintext_wallets().await? {
+                println!("✅ All wallet keys are already encrypted");
+                return Ok(());
+            }
+
+            let password = read_new_password(password)?;
+            let migrated = storage.migrate_plaintext_wallets(&password).await?;
+            println!("🔐 Encrypted {} wallet keys", migrated);
+        }
+
+        Some(Commands::CreateHdWallet { name, words, password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+            let password = read_new_password(password)?;
+            let mnemonic = generate_mnemonic(words)?;
+            let mut hd_wallet = HdWallet::from_mnemonic(name, &mnemonic)?;
+            let (index, wallet) = hd_wallet.derive_next()?;
+
+            storage.save_hd_wallet(&hd_wallet, &password).await?;
+            storage.save_wallet(&wallet, &password).await?;
+
+            println!("✅ HD wallet '{}' created!", hd_wallet.name);
+            println!("📝 Write down this recovery phrase and keep it offline:");
+            println!();
+            println!("  {}", mnemonic);
+            println!();
+            println!("Account #{} ({}): {}", index, HdWallet::derivation_path(index), wallet.address());
+        }
+
+        Some(Commands::DeriveAddress { name, password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+            let password = match password {
+                Some(password) => password,
+                None => rpassword::prompt_password("Wallet password: ")?,
+            };
+
+            match storage.derive_hd_account(&name, &password).await? {
+                Some((index, wallet)) => {
+                    println!("✅ Derived account #{} ({})", index, HdWallet::derivation_path(index));
+                    println!("Address: {}", wallet.address());
+                    println!("Public Key: {}", wallet.public_key());
+                }
+                None => anyhow::bail!("HD wallet '{}' not found", name),
+            }
+        }
+
+        Some(Commands::RestoreHdWallet { name, accounts, mnemonic, password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+            let mnemonic = match mnemonic {
+                Some(mnemonic) => mnemonic,
+                None => rpassword::prompt_password("Recovery phrase: ")?,
+            };
+            let password = read_new_password(password)?;
+
+            let mut hd_wallet = HdWallet::from_mnemonic(name, mnemonic.trim())?;
+            let mut wallets = Vec::new();
+            for _ in 0..accounts.max(1) {
+                wallets.push(hd_wallet.derive_next()?);
+            }
+
+            storage.save_hd_wallet(&hd_wallet, &password).await?;
+            println!("✅ HD wallet '{}' restored", hd_wallet.name);
+            for (index, wallet) in wallets {
+                storage.save_wallet(&wallet, &password).await?;
+                println!("  Account #{}: {}", index, wallet.address());
+            }
+        }
+
+        Some(Commands::ExportWallet { address, output, password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+            let address = validate_address(&address)?;
+
+            let (info, keystore) = match (
+                storage.get_wallet_info(&address).await?,
+                storage.load_encrypted_key(&address).await?,
+            ) {
+                (Some(info), Some(keystore)) => (info, keystore),
+                _ => anyhow::bail!("Wallet {} not found", address),
+            };
+
+            let password = match password {
+                Some(password) => password,
+                None => rpassword::prompt_password("Wallet password: ")?,
+            };
+            keystore.decrypt(&password)?;
+
+            let export = WalletExport {
+                name: info.name,
+                address: info.address,
+                public_key: info.public_key,
+                keystore,
+            };
+            std::fs::write(&output, serde_json::to_string_pretty(&export)?)?;
+            println!("🔐 Encrypted keystore for {} written to {}", address, output);
+        }
+
+        Some(Commands::BuildTransaction { from, to, amount, fee, nonce, data, node, output }) => {
+            let from = validate_address(&from)?;
+            let to = validate_address(&to)?;
+
+            let nonce = resolve_nonce(&from, nonce, node).await?;
+
+            let mut transaction = blockchain::Transaction::new(from, to, amount, data)?;
+            transaction.fee = fee;
+            transaction.nonce = nonce;
+
+            std::fs::write(&output, serde_json::to_string_pretty(&transaction)?)?;
+            println!("📝 Unsigned transaction {} written to {}", transaction.id, output);
+        }
+
+        Some(Commands::BuildBatchTransaction { from, outputs, fee, nonce, data, node, output }) => {
+            let from = validate_address(&from)?;
+            let outputs: Vec<TransferOutput> = serde_json::from_str(&std::fs::read_to_string(&outputs)?)?;
+            let outputs = outputs
+                .into_iter()
+                .map(|output| Ok(TransferOutput { to: validate_address(&output.to)?, amount: output.amount }))
+                .collect::<Result<Vec<_>>>()?;
+            let nonce = resolve_nonce(&from, nonce, node).await?;
+
+            let mut transaction = blockchain::Transaction::batch_transfer(from, outputs, data)?;
+            transaction.fee = fee;
+            transaction.nonce = nonce;
+
+            std::fs::write(&output, serde_json::to_string_pretty(&transaction)?)?;
+            println!(
+                "📝 Unsigned batch transfer {} paying {} recipients ({} total) written to {}",
+                transaction.id,
+                transaction.credits().len(),
+                transaction.amount,
+                output
+            );
+        }
+
+        Some(Commands::BuildKeyRotation { address, new_public_key, fee, nonce, node, output }) => {
+            let address = validate_address(&address)?;
+            let new_public_key = PublicKey::from_hex(&new_public_key)?;
+            let nonce = resolve_nonce(&address, nonce, node).await?;
+
+            let mut transaction = blockchain::Transaction::key_rotation(address, new_public_key)?;
+            transaction.fee = fee;
+            transaction.nonce = nonce;
+
+            std::fs::write(&output, serde_json::to_string_pretty(&transaction)?)?;
+            println!("📝 Unsigned key rotation {} written to {}", transaction.id, output);
+            println!("Sign it with the account's current key, then broadcast it");
+        }
+
+        Some(Commands::SignTransaction { file, keystore, password, output }) => {
+            let mut transaction: blockchain::Transaction =
+                serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+            let keystore = EncryptedKey::read_file(&keystore)?;
+
+            let password = match password {
+                Some(password) => password,
+                None => rpassword::prompt_password("Keystore password: ")?,
+            };
+            let private_key = keystore.decrypt(&password)?;
+            let wallet = Wallet::from_private_key("offline".to_string(), &private_key)?;
+
+            if transaction.multisig.is_some() {
+                let signature = wallet.sign_transaction(&transaction.signable_data()?)?;
+                transaction.add_multisig_signature(PartialSignature {
+                    public_key: wallet.public_key().clone(),
+                    signature,
+                })?;
+            } else {
+                // A rotated account signs with a key whose own address differs;
+                // only the node knows the current key, so just warn
+                if wallet.address() != transaction.from {
+                    warn!(
+                        "Keystore is for {}, but the transaction is sent from {}; it is only valid if this is the account's rotated key",
+                        wallet.address(),
+                        transaction.from
+                    );
+                }
+                transaction.sign(&wallet)?;
+            }
+
+            let output = output.unwrap_or(file);
+            std::fs::write(&output, serde_json::to_string_pretty(&transaction)?)?;
+            println!("✍️ Signed transaction {} written to {}", transaction.id, output);
+        }
+
+        Some(Commands::BroadcastTransaction { file, node }) => {
+            let transaction: blockchain::Transaction =
+                serde_json::from_str(&std::fs::read_to_string(&file)?)?;
+
+            let submission = NodeClient::new(&node)?.submit_signed_transaction(&transaction).await?;
+            println!("📡 Transaction {} accepted by {}", submission.transaction.id, node);
+            if let Some(replaced) = submission.replaced_transaction_id {
+                println!("🔁 Replaced pending transaction {}", replaced);
+            }
+        }
+
+        Some(Commands::CreateMultisig { threshold, keys }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+
+            let mut public_keys = Vec::new();
+            for key in keys {
+                let public_key = match validate_address(&key) {
+                    Ok(address) => match storage.get_wallet_info(&address).await? {
+                        Some(info) => PublicKey::from_hex(&info.public_key)?,
+                        None => anyhow::bail!("Wallet {} not found", address),
+                    },
+                    Err(_) => PublicKey::from_hex(&key)?,
+                };
+                public_keys.push(public_key);
+            }
+
+            let account = MultisigAccount::new(threshold, public_keys)?;
+            storage.save_multisig_account(&account).await?;
+
+            println!("✅ Multisig account created!");
+            println!("Address: {}", account.address());
+            println!("Threshold: {} of {}", account.threshold, account.public_keys.len());
+        }
+
+        Some(Commands::SignMultisig { proposal_id, wallet, password }) => {
+            let storage = BlockchainStorage::create_file("blockchain.db").await?;
+            let address = validate_address(&wallet)?;
+
+            let mut transaction = match storage.load_multisig_proposal(&proposal_id).await? {
+                Some(transaction) => transaction,
+                None => anyhow::bail!("Multisig proposal {} not found", proposal_id),
+            };
+
+            let password = match password {
+                Some(password) => password,
+                None => rpassword::prompt_password("Wallet password: ")?,
+            };
+            let wallet = match storage.load_wallet(&address, &password).await? {
+                Some(wallet) => wallet,
+                None => anyhow::bail!("Wallet {} not found", address),
+            };
+
+            let signature = wallet.sign_transaction(&transaction.signable_data()?)?;
+            transaction.add_multisig_signature(PartialSignature {
+                public_key: wallet.public_key().clone(),
+                signature,
+            })?;
+            storage.save_multisig_proposal(&transaction).await?;
+
+            if let Some(multisig) = &transaction.multisig {
+                println!(
+                    "✅ Signature added ({} of {} collected)",
+                    multisig.signatures.len(),
+                    multisig.account.threshold
+                );
+                if multisig.is_complete() {
+                    println!("💡 Submit it with POST /api/multisig/proposals/{}/submit", proposal_id);
+                }
+            }
         }
 
-        Some(Commands::Mine { miner_address: _, difficulty: _ }) => {
+        Some(Commands::Mine { miner_address, difficulty: _ }) => {
+            validate_address(&miner_address)?;
             println!("⛏️ Mining is not implemented in CLI mode. Use the full node or API.");
         }
 
         Some(Commands::Transaction { from, to, amount, data }) => {
+            let from = validate_address(&from)?;
+            let to = validate_address(&to)?;
             let storage = BlockchainStorage::create_file("blockchain.db").await?;
             let _blockchain = Blockchain::new()?;
 
@@ -193,6 +607,7 @@ async fn main() -> Result<()> {
         }
 
         Some(Commands::Balance { address }) => {
+            let address = validate_address(&address)?;
             let blockchain = Blockchain::new()?;
             let balance = blockchain.get_balance(&address);
             println!("💰 Balance for {}: {}", address, balance);
@@ -208,11 +623,13 @@ async fn main() -> Result<()> {
             }
         }
 
-        Some(Commands::DeployContract { name: _, wasm_file: _, owner: _ }) => {
+        Some(Commands::DeployContract { name: _, wasm_file: _, owner }) => {
+            validate_address(&owner)?;
             println!("📝 Contract deployment is not implemented in CLI mode. Use the API.");
         }
 
-        Some(Commands::CallContract { contract_id: _, function: _, caller: _ }) => {
+        Some(Commands::CallContract { contract_id: _, function: _, caller }) => {
+            validate_address(&caller)?;
             println!("📞 Contract calling is not implemented in CLI mode. Use the API.");
         }
     }
@@ -220,7 +637,12 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn start_full_node(api_port: u16, p2p_port: u16, database_path: &str) -> Result<()> {
+async fn start_full_node(
+    api_port: u16,
+    p2p_port: u16,
+    database_path: &str,
+    signer_socket: Option<&str>,
+) -> Result<()> {
     let path = Path::new(database_path);
     let absolute_path = if path.is_absolute() {
         path.to_path_buf()
@@ -228,32 +650,23 @@ async fn start_full_node(api_port: u16, p2p_port: u16, database_path: &str) -> R
         std::env::current_dir()?.join(path)
     };
 
-    let storage = BlockchainStorage::create_file(&absolute_path).await?;
-    let blockchain = Arc::new(RwLock::new(Blockchain::new()?));
-    let contract_engine = Arc::new(RwLock::new(ContractEngine::new()?));
+    let config = load_config()?;
+    let storage = Arc::new(BlockchainStorage::create_file(&absolute_path).await?);
+    let blockchain = restore_blockchain(&storage).await?;
+    let mempool = Arc::new(RwLock::new(restore_mempool(&storage, &blockchain, config.mempool.clone()).await?));
+    let blockchain = Arc::new(RwLock::new(blockchain));
+    let contract_engine = Arc::new(RwLock::new(restore_contracts(&storage, config.contracts).await?));
     let mining_stats = Arc::new(RwLock::new(
         storage.load_mining_stats().await?.unwrap_or_default()
     ));
     let network_stats = Arc::new(RwLock::new(NetworkStats::default()));
-    let wallets = Arc::new(RwLock::new(HashMap::new()));
+    let keyring = Arc::new(RwLock::new(create_keyring(config.keystore, signer_socket)?));
 
-    // Load existing wallets
-    let wallet_list = storage.list_wallets().await?;
-    for wallet_info in wallet_list {
-        if let Ok(Some(wallet)) = storage.load_wallet(&wallet_info.address).await {
-            wallets.write().await.insert(wallet.address(), wallet);
-        }
+    // Wallets stay locked until unlocked through the API
+    if storage.has_plaintext_wallets().await? {
+        warn!("Wallet keys are stored in plaintext, run `blockchain migrate-wallets` to encrypt them");
     }
 
-    let api_state = ApiState {
-        blockchain: blockchain.clone(),
-        storage: Arc::new(storage),
-        contract_engine,
-        mining_stats,
-        network_stats: network_stats.clone(),
-        wallets,
-    };
-
     // Start P2P network
     let network_config = NetworkConfig {
         listen_port: p2p_port,
@@ -262,6 +675,17 @@ async fn start_full_node(api_port: u16, p2p_port: u16, database_path: &str) -> R
 
     let (mut p2p_node, mut event_receiver) = P2PNode::new(network_config).await?;
 
+    let api_state = ApiState {
+        blockchain: blockchain.clone(),
+        storage: storage.clone(),
+        contract_engine,
+        mempool: mempool.clone(),
+        mining_stats,
+        network_stats: network_stats.clone(),
+        keyring,
+        network_events: Some(p2p_node.event_sender()),
+    };
+
     // Spawn P2P network task
     tokio::spawn(async move {
         p2p_node.run().await;
@@ -275,13 +699,16 @@ async fn start_full_node(api_port: u16, p2p_port: u16, database_path: &str) -> R
         }
     });
 
+    spawn_mempool_persistence(storage.clone(), mempool.clone(), &config.mempool);
+
     // Start API server
     start_server(api_state, api_port).await?;
+    persist_mempool(&storage, &mempool).await;
 
     Ok(())
 }
 
-async fn start_api_server(port: u16, database_path: &str) -> Result<()> {
+async fn start_api_server(port: u16, database_path: &str, signer_socket: Option<&str>) -> Result<()> {
     let path = Path::new(database_path);
     let absolute_path = if path.is_absolute() {
         path.to_path_buf()
@@ -289,24 +716,147 @@ async fn start_api_server(port: u16, database_path: &str) -> Result<()> {
         std::env::current_dir()?.join(path)
     };
 
-    let storage = BlockchainStorage::create_file(&absolute_path).await?;
-    let blockchain = Arc::new(RwLock::new(Blockchain::new()?));
-    let contract_engine = Arc::new(RwLock::new(ContractEngine::new()?));
+    let config = load_config()?;
+    let storage = Arc::new(BlockchainStorage::create_file(&absolute_path).await?);
+    let blockchain = restore_blockchain(&storage).await?;
+    let mempool = Arc::new(RwLock::new(restore_mempool(&storage, &blockchain, config.mempool.clone()).await?));
+    let blockchain = Arc::new(RwLock::new(blockchain));
+    let contract_engine = Arc::new(RwLock::new(restore_contracts(&storage, config.contracts).await?));
     let mining_stats = Arc::new(RwLock::new(
         storage.load_mining_stats().await?.unwrap_or_default()
     ));
     let network_stats = Arc::new(RwLock::new(NetworkStats::default()));
-    let wallets = Arc::new(RwLock::new(HashMap::new()));
+    let keyring = Arc::new(RwLock::new(create_keyring(config.keystore, signer_socket)?));
 
     let api_state = ApiState {
         blockchain,
-        storage: Arc::new(storage),
+        storage: storage.clone(),
         contract_engine,
+        mempool: mempool.clone(),
         mining_stats,
         network_stats,
-        wallets,
+        keyring,
+        network_events: None,
     };
 
+    spawn_mempool_persistence(storage.clone(), mempool.clone(), &config.mempool);
+
     start_server(api_state, port).await?;
+    persist_mempool(&storage, &mempool).await;
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Reads the node configuration from the file named by `BLOCKCHAIN_CONFIG`,
+/// or uses the defaults if it is unset.
+fn load_config() -> Result<BlockchainConfig> {
+    let Ok(path) = std::env::var("BLOCKCHAIN_CONFIG") else {
+        return Ok(BlockchainConfig::default());
+    };
+    let config = BlockchainConfig::from_file(&path)?;
+    config.validate()?;
+    info!("Loaded configuration from {}", path);
+    Ok(config)
+}
+
+/// Loads the stored chain with the outcomes of its contract transactions, or
+/// starts a new one and stores its genesis block if the database has none.
+async fn restore_blockchain(storage: &BlockchainStorage) -> Result<Blockchain> {
+    let blocks = storage.load_all_blocks().await?;
+    if blocks.is_empty() {
+        let blockchain = Blockchain::new()?;
+        storage.save_block(blockchain.get_latest_block()?).await?;
+        info!("Started a new chain");
+        return Ok(blockchain);
+    }
+
+    let mut blockchain = Blockchain::from_blocks(blocks)?;
+    blockchain.record_contract_receipts(&storage.load_contract_receipts().await?);
+    info!("Restored chain of {} blocks", blockchain.len());
+    Ok(blockchain)
+}
+
+async fn restore_mempool(storage: &BlockchainStorage, blockchain: &Blockchain, config: MempoolConfig) -> Result<Mempool> {
+    let mut mempool = Mempool::new(config);
+    let pending = storage.load_mempool().await?;
+    let total = pending.len();
+
+    let dropped = mempool.restore(pending, blockchain);
+    info!("Restored {} pending transactions ({} dropped as invalid)", total - dropped, dropped);
+
+    Ok(mempool)
+}
+
+async fn restore_contracts(storage: &BlockchainStorage, config: ContractsConfig) -> Result<ContractEngine> {
+    let mut engine = ContractEngine::with_config(config)?;
+    let contracts = storage.load_contracts().await?;
+    let total = contracts.len();
+
+    for (contract, state) in contracts {
+        engine.restore_contract(contract, state)?;
+    }
+    info!("Restored {} contracts", total);
+
+    Ok(engine)
+}
+
+fn spawn_mempool_persistence(storage: Arc<BlockchainStorage>, mempool: Arc<RwLock<Mempool>>, config: &MempoolConfig) {
+    let period = Duration::from_secs(config.persist_interval_secs);
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            persist_mempool(&storage, &mempool).await;
+        }
+    });
+}
+
+async fn persist_mempool(storage: &BlockchainStorage, mempool: &RwLock<Mempool>) {
+    let transactions: Vec<_> = mempool.read().await.transactions().cloned().collect();
+
+    if let Err(e) = storage.save_mempool(&transactions).await {
+        warn!("Failed to persist mempool: {}", e);
+    }
+}
+
+/// Returns `nonce` if given, otherwise asks the node at `node` for the
+/// sender's next nonce.
+async fn resolve_nonce(address: &str, nonce: Option<u64>, node: Option<String>) -> Result<u64> {
+    match (nonce, node) {
+        (Some(nonce), _) => Ok(nonce),
+        (None, Some(node)) => Ok(NodeClient::new(&node)?.next_nonce(address).await?),
+        (None, None) => anyhow::bail!("Pass --nonce, or --node to look up the next nonce"),
+    }
+}
+
+fn create_keyring(config: KeystoreConfig, signer_socket: Option<&str>) -> Result<Keyring> {
+    let mut keyring = Keyring::new(config);
+
+    if let Some(socket) = signer_socket {
+        #[cfg(unix)]
+        {
+            let signer = RemoteSigner::connect(socket)?;
+            keyring.attach(Wallet::with_signer("remote signer".to_string(), Arc::new(signer)));
+        }
+        #[cfg(not(unix))]
+        anyhow::bail!("Remote signers are only supported on Unix, cannot use {}", socket);
+    }
+
+    Ok(keyring)
+}
+
+fn read_new_password(provided: Option<String>) -> Result<String> {
+    if let Some(password) = provided {
+        return Ok(password);
+    }
+
+    let password = rpassword::prompt_password("Wallet password: ")?;
+    let confirmation = rpassword::prompt_password("Confirm password: ")?;
+    if password != confirmation {
+        anyhow::bail!("Passwords do not match");
+    }
+
+    Ok(password)
+}
@@ -0,0 +1,391 @@
+use crate::block::Block;
+use crate::blockchain::Blockchain;
+use crate::config::MempoolConfig;
+use crate::errors::{BlockchainError, Result};
+use crate::transaction::Transaction;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use tracing::{debug, info, warn};
+
+/// Lower bounds, in fee per serialized byte, of the fee rate histogram buckets.
+pub const FEE_RATE_BUCKETS: [f64; 6] = [0.0, 0.000_01, 0.000_1, 0.001, 0.01, 0.1];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeRateBucket {
+    pub min_fee_rate: f64,
+    pub max_fee_rate: Option<f64>,
+    pub count: usize,
+    pub total_size: usize,
+}
+
+pub struct Mempool {
+    config: MempoolConfig,
+    transactions: HashMap<String, Transaction>,
+    by_sender_nonce: HashMap<(String, u64), String>,
+}
+
+impl Mempool {
+    pub fn new(config: MempoolConfig) -> Self {
+        Mempool {
+            config,
+            transactions: HashMap::new(),
+            by_sender_nonce: HashMap::new(),
+        }
+    }
+
+    /// Admits a signed transaction, returning the pending transaction it replaced
+    /// if one with the same sender and nonce was already in the pool.
+    pub fn add_transaction(
+        &mut self,
+        transaction: Transaction,
+        blockchain: &Blockchain,
+    ) -> Result<Option<Transaction>> {
+        self.validate(&transaction, blockchain)?;
+
+        let slot = (transaction.from.clone(), transaction.nonce);
+        let replaced = match self.by_sender_nonce.get(&slot) {
+            Some(existing_id) => {
+                let existing = &self.transactions[existing_id];
+                let required_fee = existing.fee * (1.0 + self.config.min_fee_bump_percent / 100.0);
+
+                if transaction.fee <= existing.fee || transaction.fee < required_fee {
+                    return Err(BlockchainError::InvalidTransaction {
+                        message: format!(
+                            "Replacement fee {} too low, must be at least {} to replace {}",
+                            transaction.fee, required_fee, existing.id
+                        ),
+                    });
+                }
+
+                let existing_id = existing_id.clone();
+                self.transactions.remove(&existing_id)
+            }
+            None => {
+                if self.transactions.len() >= self.config.max_transactions {
+                    return Err(BlockchainError::InvalidTransaction {
+                        message: "Mempool is full".to_string(),
+                    });
+                }
+                None
+            }
+        };
+
+        if let Some(old) = &replaced {
+            info!(
+                "Transaction {} replaced by {} (fee {} -> {})",
+                old.id, transaction.id, old.fee, transaction.fee
+            );
+        } else {
+            debug!("Transaction {} added to mempool", transaction.id);
+        }
+
+        self.by_sender_nonce.insert(slot, transaction.id.clone());
+        self.transactions.insert(transaction.id.clone(), transaction);
+
+        Ok(replaced)
+    }
+
+    fn validate(&self, transaction: &Transaction, blockchain: &Blockchain) -> Result<()> {
+        if transaction.is_coinbase() {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Coinbase transactions cannot enter the mempool".to_string(),
+            });
+        }
+
+        if self.transactions.contains_key(&transaction.id) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} is already pending", transaction.id),
+            });
+        }
+
+        if transaction.fee < 0.0 {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Fee cannot be negative".to_string(),
+            });
+        }
+
+        if let Some(multisig) = &transaction.multisig {
+            if !transaction.verify_signature() {
+                return Err(BlockchainError::InvalidTransaction {
+                    message: format!(
+                        "Multisig transaction needs {} valid signatures from the sender's keys",
+                        multisig.account.threshold
+                    ),
+                });
+            }
+        } else {
+            if transaction.from_public_key.is_none() || !transaction.verify_signature() {
+                return Err(BlockchainError::InvalidTransaction {
+                    message: "Transaction must carry a valid signature".to_string(),
+                });
+            }
+            blockchain.check_signer(transaction)?;
+        }
+
+        transaction.check_kind()?;
+        transaction.check_lock_window()?;
+
+        // Time- and height-locked transactions wait here until they unlock
+        if transaction.is_expired(blockchain.next_index(), Utc::now()) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} has expired", transaction.id),
+            });
+        }
+
+        let next_nonce = blockchain.next_nonce(&transaction.from);
+        if transaction.nonce < next_nonce {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!(
+                    "Nonce {} already used, next nonce for {} is {}",
+                    transaction.nonce, transaction.from, next_nonce
+                ),
+            });
+        }
+
+        // A transaction is paid in full or not at all
+        let available = self.available_balance(transaction, blockchain);
+        let required = transaction.amount + transaction.fee;
+        if available < required {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!(
+                    "Transaction needs {} but {} has {} after its earlier pending transactions",
+                    required, transaction.from, available
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Balance the sender of `transaction` is sure to have for it in a block:
+    /// the confirmed balance less what its pending transactions with lower
+    /// nonces spend. Pending incoming funds don't count, since a block may
+    /// order them after `transaction`.
+    fn available_balance(&self, transaction: &Transaction, blockchain: &Blockchain) -> f64 {
+        let earlier_delta: f64 = self
+            .transactions
+            .values()
+            .filter(|tx| tx.from == transaction.from && tx.nonce < transaction.nonce)
+            .map(|tx| tx.amount_to(&tx.from) - tx.amount - tx.fee)
+            .sum();
+
+        blockchain.get_balance(&transaction.from) + earlier_delta
+    }
+
+    /// Re-admits persisted transactions against the current tip, returning how
+    /// many were dropped because they are no longer valid.
+    pub fn restore(&mut self, transactions: Vec<Transaction>, blockchain: &Blockchain) -> usize {
+        let mut dropped = 0;
+
+        for transaction in transactions {
+            let id = transaction.id.clone();
+            if let Err(e) = self.add_transaction(transaction, blockchain) {
+                warn!("Dropping persisted transaction {}: {}", id, e);
+                dropped += 1;
+            }
+        }
+
+        dropped
+    }
+
+    /// Next nonce for `sender`, accounting for both confirmed and pending transactions.
+    pub fn pending_nonce(&self, sender: &str, blockchain: &Blockchain) -> u64 {
+        self.by_sender_nonce
+            .keys()
+            .filter(|(from, _)| from == sender)
+            .map(|(_, nonce)| nonce + 1)
+            .max()
+            .unwrap_or(0)
+            .max(blockchain.next_nonce(sender))
+    }
+
+    pub fn get_transaction(&self, id: &str) -> Option<&Transaction> {
+        self.transactions.get(id)
+    }
+
+    pub fn remove_transaction(&mut self, id: &str) -> Option<Transaction> {
+        let transaction = self.transactions.remove(id)?;
+        self.by_sender_nonce
+            .remove(&(transaction.from.clone(), transaction.nonce));
+        Some(transaction)
+    }
+
+    /// Drops transactions included in `block`, along with any pending ones that
+    /// conflict with them on sender and nonce.
+    pub fn remove_confirmed(&mut self, block: &Block) {
+        for transaction in &block.transactions {
+            if let Some(id) = self
+                .by_sender_nonce
+                .get(&(transaction.from.clone(), transaction.nonce))
+                .cloned()
+            {
+                self.remove_transaction(&id);
+            }
+        }
+    }
+
+    /// Drops pending transactions that can no longer be included in a block
+    /// at `height` or later, returning how many were dropped.
+    pub fn remove_expired(&mut self, height: u64, time: DateTime<Utc>) -> usize {
+        let expired: Vec<String> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.is_expired(height, time))
+            .map(|tx| tx.id.clone())
+            .collect();
+
+        for id in &expired {
+            debug!("Transaction {} expired", id);
+            self.remove_transaction(id);
+        }
+
+        expired.len()
+    }
+
+    /// Pending transactions the next block may include at `time`. Each sender's
+    /// transactions run in nonce order from its next confirmed nonce, stopping
+    /// at the first gap or locked transaction; senders are interleaved by the
+    /// fee rate of their next transaction.
+    pub fn ready_transactions(&self, blockchain: &Blockchain, time: DateTime<Utc>) -> Vec<&Transaction> {
+        let height = blockchain.next_index();
+        let mut by_sender: HashMap<&str, Vec<&Transaction>> = HashMap::new();
+        for transaction in self.transactions.values() {
+            by_sender.entry(transaction.from.as_str()).or_default().push(transaction);
+        }
+
+        let mut queues: Vec<VecDeque<&Transaction>> = by_sender
+            .into_iter()
+            .map(|(sender, mut transactions)| {
+                transactions.sort_by_key(|tx| tx.nonce);
+                let next_nonce = blockchain.next_nonce(sender);
+                transactions
+                    .into_iter()
+                    .enumerate()
+                    .take_while(|(i, tx)| {
+                        tx.nonce == next_nonce + *i as u64 && tx.check_locks(height, time).is_ok()
+                    })
+                    .map(|(_, tx)| tx)
+                    .collect()
+            })
+            .collect();
+
+        let mut ready = Vec::new();
+        while let Some(queue) = queues
+            .iter_mut()
+            .filter(|queue| !queue.is_empty())
+            .min_by(|a, b| by_fee_rate(a[0], b[0]))
+        {
+            ready.extend(queue.pop_front());
+        }
+
+        ready
+    }
+
+    pub fn transactions(&self) -> impl Iterator<Item = &Transaction> {
+        self.transactions.values()
+    }
+
+    /// Pending transactions ordered by fee rate, highest first.
+    pub fn transactions_by_fee_rate(&self) -> Vec<&Transaction> {
+        let mut transactions: Vec<&Transaction> = self.transactions.values().collect();
+        transactions.sort_by(|a, b| by_fee_rate(a, b));
+        transactions
+    }
+
+    /// Pending transactions sent from and received by `address`, each in nonce order.
+    pub fn transactions_for_address(&self, address: &str) -> (Vec<&Transaction>, Vec<&Transaction>) {
+        let mut outgoing: Vec<&Transaction> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.from == address)
+            .collect();
+        outgoing.sort_by_key(|tx| tx.nonce);
+
+        let mut incoming: Vec<&Transaction> = self
+            .transactions
+            .values()
+            .filter(|tx| tx.credits().iter().any(|(to, _)| *to == address))
+            .collect();
+        incoming.sort_by_key(|tx| tx.timestamp);
+
+        (outgoing, incoming)
+    }
+
+    /// Confirmed balance of `address` adjusted for everything currently pending.
+    pub fn projected_balance(&self, address: &str, blockchain: &Blockchain) -> f64 {
+        let pending_delta: f64 = self
+            .transactions
+            .values()
+            .map(|tx| {
+                let mut delta = tx.amount_to(address);
+                if tx.from == address {
+                    delta -= tx.amount + tx.fee;
+                }
+                delta
+            })
+            .sum();
+
+        blockchain.get_balance(address) + pending_delta
+    }
+
+    pub fn total_size(&self) -> usize {
+        self.transactions.values().map(|tx| tx.size()).sum()
+    }
+
+    pub fn total_fees(&self) -> f64 {
+        self.transactions.values().map(|tx| tx.fee).sum()
+    }
+
+    pub fn fee_rate_histogram(&self) -> Vec<FeeRateBucket> {
+        let mut buckets: Vec<FeeRateBucket> = FEE_RATE_BUCKETS
+            .iter()
+            .enumerate()
+            .map(|(i, &min_fee_rate)| FeeRateBucket {
+                min_fee_rate,
+                max_fee_rate: FEE_RATE_BUCKETS.get(i + 1).copied(),
+                count: 0,
+                total_size: 0,
+            })
+            .collect();
+
+        for transaction in self.transactions.values() {
+            let fee_rate = transaction.fee_rate();
+            let index = FEE_RATE_BUCKETS
+                .iter()
+                .rposition(|&min| fee_rate >= min)
+                .unwrap_or(0);
+
+            buckets[index].count += 1;
+            buckets[index].total_size += transaction.size();
+        }
+
+        buckets
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transactions.is_empty()
+    }
+
+    pub fn config(&self) -> &MempoolConfig {
+        &self.config
+    }
+}
+
+/// Orders transactions by fee rate, highest first, then oldest first.
+fn by_fee_rate(a: &Transaction, b: &Transaction) -> Ordering {
+    b.fee_rate()
+        .total_cmp(&a.fee_rate())
+        .then_with(|| a.timestamp.cmp(&b.timestamp))
+}
+
+impl Default for Mempool {
+    fn default() -> Self {
+        Self::new(MempoolConfig::default())
+    }
+}
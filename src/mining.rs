@@ -1,8 +1,9 @@
 use crate::block::Block;
-use crate::crypto::Wallet;
 use crate::errors::{BlockchainError, Result};
+use crate::signer::Signer;
 use crate::transaction::Transaction;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{debug, info};
 
@@ -37,12 +38,12 @@ pub struct MiningResult {
 
 pub struct Miner {
     config: MiningConfig,
-    wallet: Wallet,
+    signer: Arc<dyn Signer>,
 }
 
 impl Miner {
-    pub fn new(config: MiningConfig, wallet: Wallet) -> Self {
-        Miner { config, wallet }
+    pub fn new(config: MiningConfig, signer: Arc<dyn Signer>) -> Self {
+        Miner { config, signer }
     }
 
     pub fn mine_block(
@@ -55,15 +56,12 @@ impl Miner {
         info!("Starting to mine block #{}", index);
 
         let mut block_transactions = transactions;
+        let total_fees: f64 = block_transactions.iter().map(|tx| tx.fee).sum();
 
-        let coinbase_transaction = Transaction::new_signed(
-            "miner".to_string(),
-            self.wallet.address(),
-            self.config.block_reward,
-            Some("Block reward".to_string()),
-            self.wallet.sign_transaction(b"coinbase"),
-            self.wallet.keypair.public_key().clone(),
-        )?;
+        let mut coinbase_transaction =
+            Transaction::coinbase(self.reward_address(), self.config.block_reward + total_fees)?;
+        coinbase_transaction.signature = Some(self.signer.sign(b"coinbase")?);
+        coinbase_transaction.from_public_key = Some(self.signer.public_key().clone());
 
         block_transactions.insert(0, coinbase_transaction);
 
@@ -181,8 +179,9 @@ impl Miner {
         &self.config
     }
 
-    pub fn wallet(&self) -> &Wallet {
-        &self.wallet
+    /// Address block rewards are paid to, derived from the signer's key.
+    pub fn reward_address(&self) -> String {
+        self.signer.public_key().to_address()
     }
 
     pub fn set_difficulty(&mut self, difficulty: u32) {
@@ -0,0 +1,147 @@
+use crate::address::{Address, Network, ADDRESS_VERSION_MULTISIG};
+use crate::crypto::{DigitalSignature, PublicKey};
+use crate::errors::{BlockchainError, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Upper bound on the number of keys in a multisig account.
+pub const MAX_MULTISIG_KEYS: usize = 16;
+
+/// An M-of-N account. Keys are kept sorted so the same set of keys and
+/// threshold always yields the same address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultisigAccount {
+    pub threshold: usize,
+    pub public_keys: Vec<PublicKey>,
+}
+
+impl MultisigAccount {
+    pub fn new(threshold: usize, mut public_keys: Vec<PublicKey>) -> Result<Self> {
+        public_keys.sort_by_key(|key| key.to_bytes());
+
+        let account = MultisigAccount {
+            threshold,
+            public_keys,
+        };
+        account.validate()?;
+
+        Ok(account)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.public_keys.is_empty() || self.public_keys.len() > MAX_MULTISIG_KEYS {
+            return Err(BlockchainError::Wallet {
+                message: format!("Multisig accounts need between 1 and {} keys", MAX_MULTISIG_KEYS),
+            });
+        }
+
+        if self.threshold == 0 || self.threshold > self.public_keys.len() {
+            return Err(BlockchainError::Wallet {
+                message: format!(
+                    "Threshold must be between 1 and {}, got {}",
+                    self.public_keys.len(),
+                    self.threshold
+                ),
+            });
+        }
+
+        if self
+            .public_keys
+            .windows(2)
+            .any(|pair| pair[0].to_bytes() >= pair[1].to_bytes())
+        {
+            return Err(BlockchainError::Wallet {
+                message: "Multisig keys must be distinct".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn address(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update([self.threshold as u8]);
+        for key in &self.public_keys {
+            hasher.update(key.to_bytes());
+        }
+
+        Address::new(Network::default(), ADDRESS_VERSION_MULTISIG, hasher.finalize().into()).to_string()
+    }
+
+    pub fn contains(&self, public_key: &PublicKey) -> bool {
+        self.public_keys.contains(public_key)
+    }
+}
+
+/// One co-signer's signature over a transaction's signable data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartialSignature {
+    pub public_key: PublicKey,
+    pub signature: DigitalSignature,
+}
+
+/// The account descriptor and signatures collected so far for a transaction
+/// spending from a multisig address.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MultisigAuthorization {
+    pub account: MultisigAccount,
+    pub signatures: Vec<PartialSignature>,
+}
+
+impl MultisigAuthorization {
+    pub fn new(account: MultisigAccount) -> Self {
+        MultisigAuthorization {
+            account,
+            signatures: Vec::new(),
+        }
+    }
+
+    /// Adds a co-signer's signature after checking it belongs to the account,
+    /// is valid for `message` and has not been collected already.
+    pub fn add_signature(&mut self, message: &[u8], partial: PartialSignature) -> Result<()> {
+        if !self.account.contains(&partial.public_key) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Signer is not a member of the multisig account".to_string(),
+            });
+        }
+
+        if self.signatures.iter().any(|s| s.public_key == partial.public_key) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Signer has already signed this transaction".to_string(),
+            });
+        }
+
+        if !partial.public_key.verify(message, &partial.signature) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Invalid partial signature".to_string(),
+            });
+        }
+
+        self.signatures.push(partial);
+        Ok(())
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.signatures.len() >= self.account.threshold
+    }
+
+    /// Whether the account hashes to `address` and at least `threshold`
+    /// distinct members produced valid signatures over `message`.
+    pub fn verify(&self, address: &str, message: &[u8]) -> bool {
+        if self.account.validate().is_err() || self.account.address() != address {
+            return false;
+        }
+
+        let mut signers: Vec<&PublicKey> = Vec::new();
+        for partial in &self.signatures {
+            if self.account.contains(&partial.public_key)
+                && !signers.contains(&&partial.public_key)
+                && partial.public_key.verify(message, &partial.signature)
+            {
+                signers.push(&partial.public_key);
+            }
+        }
+
+        signers.len() >= self.account.threshold
+    }
+}
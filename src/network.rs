@@ -14,6 +14,7 @@ use tracing::{debug, info};
 pub enum BlockchainMessage {
     NewBlock(Block),
     NewTransaction(Transaction),
+    TransactionReplaced { replaced_id: String, replacement: Transaction },
     BlockRequest { from_index: u64, to_index: u64 },
     BlockResponse { blocks: Vec<Block> },
     PeerList { peers: Vec<String> },
@@ -32,6 +33,7 @@ pub struct P2PNode {
 pub enum P2PEvent {
     NewBlock(Block),
     NewTransaction(Transaction),
+    TransactionReplaced { replaced_id: String, replacement: Transaction },
     PeerConnected(String),
     PeerDisconnected(String),
     BlockRequest { peer: String, from_index: u64, to_index: u64 },
@@ -101,6 +103,19 @@ impl P2PNode {
         Ok(())
     }
 
+    pub fn broadcast_replacement(&mut self, replaced_id: &str, replacement: &Transaction) -> Result<()> {
+        info!(
+            "Simulated broadcast of transaction {} replacing {} to network",
+            replacement.id, replaced_id
+        );
+
+        let _ = self.event_sender.send(P2PEvent::TransactionReplaced {
+            replaced_id: replaced_id.to_string(),
+            replacement: replacement.clone(),
+        });
+        Ok(())
+    }
+
     pub fn request_blocks(&mut self, peer: String, from_index: u64, to_index: u64) -> Result<()> {
         info!("Simulated request for blocks {}-{} from peer {}", from_index, to_index, peer);
         Ok(())
@@ -115,6 +130,10 @@ impl P2PNode {
         Ok(())
     }
 
+    pub fn event_sender(&self) -> mpsc::UnboundedSender<P2PEvent> {
+        self.event_sender.clone()
+    }
+
     pub fn connected_peers(&self) -> Vec<String> {
         self.peers.iter().cloned().collect()
     }
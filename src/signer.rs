@@ -0,0 +1,202 @@
+use crate::crypto::{DigitalSignature, KeyPair, PublicKey};
+use crate::errors::{BlockchainError, Result};
+use serde::{Deserialize, Serialize};
+
+/// Produces signatures for one public key. `Wallet` and `Miner` sign through
+/// this so the key itself can live outside the node process.
+pub trait Signer: Send + Sync {
+    fn public_key(&self) -> &PublicKey;
+
+    fn sign(&self, message: &[u8]) -> Result<DigitalSignature>;
+
+    /// The key material, for signers that hold it in this process. Remote
+    /// signers return `None`, so such wallets cannot be stored or exported.
+    fn keypair(&self) -> Option<&KeyPair> {
+        None
+    }
+}
+
+/// In-process signer.
+impl Signer for KeyPair {
+    fn public_key(&self) -> &PublicKey {
+        KeyPair::public_key(self)
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<DigitalSignature> {
+        Ok(KeyPair::sign(self, message))
+    }
+
+    fn keypair(&self) -> Option<&KeyPair> {
+        Some(self)
+    }
+}
+
+/// One line of the signer socket protocol, sent by the node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum SignerRequest {
+    PublicKey,
+    Sign { message: String },
+}
+
+/// One line of the signer socket protocol, sent by the signer daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignerResponse {
+    PublicKey { public_key: String },
+    Signature { signature: String },
+    Error { message: String },
+}
+
+#[cfg(unix)]
+pub use remote::{serve, RemoteSigner};
+
+#[cfg(unix)]
+mod remote {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+    use tracing::{debug, info, warn};
+
+    const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Signs by forwarding requests to a signer daemon over a Unix socket, so
+    /// the private key never enters this process.
+    pub struct RemoteSigner {
+        socket_path: PathBuf,
+        public_key: PublicKey,
+    }
+
+    impl RemoteSigner {
+        /// Connects to the daemon at `socket_path` and fetches its public key.
+        pub fn connect(socket_path: impl AsRef<Path>) -> Result<Self> {
+            let socket_path = socket_path.as_ref().to_path_buf();
+            let public_key = match request(&socket_path, &SignerRequest::PublicKey)? {
+                SignerResponse::PublicKey { public_key } => PublicKey::from_hex(&public_key)?,
+                other => return Err(unexpected(other)),
+            };
+
+            info!("Connected to remote signer at {} for {}", socket_path.display(), public_key.to_address());
+            Ok(RemoteSigner {
+                socket_path,
+                public_key,
+            })
+        }
+
+        pub fn socket_path(&self) -> &Path {
+            &self.socket_path
+        }
+    }
+
+    impl Signer for RemoteSigner {
+        fn public_key(&self) -> &PublicKey {
+            &self.public_key
+        }
+
+        fn sign(&self, message: &[u8]) -> Result<DigitalSignature> {
+            let signature = match request(
+                &self.socket_path,
+                &SignerRequest::Sign {
+                    message: hex::encode(message),
+                },
+            )? {
+                SignerResponse::Signature { signature } => DigitalSignature::from_hex(&signature)?,
+                other => return Err(unexpected(other)),
+            };
+
+            // Don't trust the daemon blindly: a bad signature would only be
+            // caught later, when the transaction is rejected.
+            if !self.public_key.verify(message, &signature) {
+                return Err(signer_error("Remote signer returned an invalid signature".to_string()));
+            }
+
+            Ok(signature)
+        }
+    }
+
+    fn request(socket_path: &Path, request: &SignerRequest) -> Result<SignerResponse> {
+        let stream = UnixStream::connect(socket_path).map_err(|e| {
+            signer_error(format!("Failed to connect to signer at {}: {}", socket_path.display(), e))
+        })?;
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        stream.set_write_timeout(Some(IO_TIMEOUT))?;
+
+        let mut line = serde_json::to_string(request)?;
+        line.push('\n');
+        (&stream).write_all(line.as_bytes())?;
+
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response)?;
+        if response.is_empty() {
+            return Err(signer_error("Signer closed the connection".to_string()));
+        }
+
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    fn unexpected(response: SignerResponse) -> BlockchainError {
+        match response {
+            SignerResponse::Error { message } => signer_error(format!("Signer refused request: {}", message)),
+            other => signer_error(format!("Unexpected signer response: {:?}", other)),
+        }
+    }
+
+    /// Serves signing requests for `signer` on `listener`, one thread per
+    /// connection. Runs until accepting a connection fails.
+    pub fn serve(listener: UnixListener, signer: Arc<dyn Signer>) -> Result<()> {
+        loop {
+            let (stream, _) = listener.accept()?;
+            let signer = signer.clone();
+            thread::spawn(move || {
+                if let Err(e) = handle_connection(stream, signer.as_ref()) {
+                    warn!("Signer connection failed: {}", e);
+                }
+            });
+        }
+    }
+
+    fn handle_connection(stream: UnixStream, signer: &dyn Signer) -> Result<()> {
+        stream.set_read_timeout(Some(IO_TIMEOUT))?;
+        let reader = BufReader::new(&stream);
+
+        for line in reader.lines() {
+            let line = line?;
+            let response = match serde_json::from_str::<SignerRequest>(&line) {
+                Ok(SignerRequest::PublicKey) => SignerResponse::PublicKey {
+                    public_key: signer.public_key().to_string(),
+                },
+                Ok(SignerRequest::Sign { message }) => match hex::decode(&message) {
+                    Ok(message) => {
+                        debug!("Signing {} byte message", message.len());
+                        match signer.sign(&message) {
+                            Ok(signature) => SignerResponse::Signature {
+                                signature: signature.to_string(),
+                            },
+                            Err(e) => SignerResponse::Error { message: e.to_string() },
+                        }
+                    }
+                    Err(e) => SignerResponse::Error {
+                        message: format!("Message is not valid hex: {}", e),
+                    },
+                },
+                Err(e) => SignerResponse::Error {
+                    message: format!("Malformed request: {}", e),
+                },
+            };
+
+            let mut response = serde_json::to_string(&response)?;
+            response.push('\n');
+            (&stream).write_all(response.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    fn signer_error(message: String) -> BlockchainError {
+        BlockchainError::Io(std::io::Error::other(message))
+    }
+}
@@ -1,12 +1,20 @@
 use crate::block::Block;
-use crate::crypto::Wallet;
+use crate::contracts::{ContractChanges, ContractReceipt, ContractState, ContractValue, SmartContract};
+use crate::crypto::{PublicKey, Wallet};
+use crate::hdwallet::HdWallet;
+use crate::keystore::EncryptedKey;
 use crate::errors::{BlockchainError, Result};
 use crate::mining::MiningStats;
+use crate::multisig::MultisigAccount;
+use crate::transaction::Transaction;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
 use sqlx::{Row, SqlitePool};
+use std::collections::HashMap;
 use std::path::Path;
 use tracing::{debug, info};
+use zeroize::Zeroizing;
 
 #[derive(Clone)]
 pub struct BlockchainStorage {
@@ -90,13 +98,114 @@ impl BlockchainStorage {
             format!("Failed to create transactions table: {}", e),
         )))?;
 
+        // One row per credited recipient, so batch transfers are indexed by
+        // every address they pay.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS transaction_outputs (
+                transaction_id TEXT NOT NULL,
+                output_index INTEGER NOT NULL,
+                block_index INTEGER NOT NULL,
+                from_address TEXT NOT NULL,
+                to_address TEXT NOT NULL,
+                amount REAL NOT NULL,
+                PRIMARY KEY (transaction_id, output_index),
+                FOREIGN KEY (block_index) REFERENCES blocks (index_num)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create transaction_outputs table: {}", e),
+        )))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS smart_contracts (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                owner TEXT NOT NULL,
+                code BLOB NOT NULL,
+                abi TEXT,
+                gas_limit INTEGER NOT NULL DEFAULT 1000000,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                deployed_at TEXT,
+                is_active BOOLEAN NOT NULL DEFAULT TRUE,
+                balance REAL NOT NULL DEFAULT 0.0
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create smart_contracts table: {}", e),
+        )))?;
+
+        // Tables created from migrations/001_initial_schema.sql have no balance
+        if !self.has_column("smart_contracts", "balance").await? {
+            sqlx::query("ALTER TABLE smart_contracts ADD COLUMN balance REAL NOT NULL DEFAULT 0.0")
+                .execute(&self.pool)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to add smart_contracts balance column: {}", e),
+                )))?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS contract_states (
+                contract_id TEXT NOT NULL,
+                key TEXT NOT NULL,
+                value TEXT NOT NULL,
+                updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                PRIMARY KEY (contract_id, key),
+                FOREIGN KEY (contract_id) REFERENCES smart_contracts(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create contract_states table: {}", e),
+        )))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS contract_receipts (
+                transaction_id TEXT PRIMARY KEY,
+                block_index INTEGER NOT NULL,
+                success BOOLEAN NOT NULL,
+                result TEXT NOT NULL,
+                FOREIGN KEY (block_index) REFERENCES blocks (index_num)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create contract_receipts table: {}", e),
+        )))?;
+
+        for index in [
+            "CREATE INDEX IF NOT EXISTS idx_transaction_outputs_to ON transaction_outputs (to_address)",
+            "CREATE INDEX IF NOT EXISTS idx_transaction_outputs_from ON transaction_outputs (from_address)",
+        ] {
+            sqlx::query(index)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to create transaction_outputs index: {}", e),
+                )))?;
+        }
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS wallets (
                 address TEXT PRIMARY KEY,
                 name TEXT NOT NULL,
                 public_key TEXT NOT NULL,
-                private_key TEXT NOT NULL,
+                private_key_encrypted TEXT NOT NULL,
                 created_at TEXT NOT NULL
             )
             "#,
@@ -108,6 +217,67 @@ impl BlockchainStorage {
             format!("Failed to create wallets table: {}", e),
         )))?;
 
+        // Databases created before keys were encrypted only have the plaintext
+        // `private_key` column; `migrate_plaintext_wallets` fills this one in.
+        if !self.has_column("wallets", "private_key_encrypted").await? {
+            sqlx::query("ALTER TABLE wallets ADD COLUMN private_key_encrypted TEXT")
+                .execute(&self.pool)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to add private_key_encrypted column: {}", e),
+                )))?;
+        }
+
+        self.migrate_wallet_addresses().await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS hd_wallets (
+                name TEXT PRIMARY KEY,
+                seed_encrypted TEXT NOT NULL,
+                next_index INTEGER NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create hd_wallets table: {}", e),
+        )))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS multisig_accounts (
+                address TEXT PRIMARY KEY,
+                threshold INTEGER NOT NULL,
+                public_keys TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create multisig_accounts table: {}", e),
+        )))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS multisig_proposals (
+                id TEXT PRIMARY KEY,
+                address TEXT NOT NULL,
+                data TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create multisig_proposals table: {}", e),
+        )))?;
+
         sqlx::query(
             r#"
             CREATE TABLE IF NOT EXISTS mining_stats (
@@ -127,11 +297,39 @@ impl BlockchainStorage {
             format!("Failed to create mining_stats table: {}", e),
         )))?;
 
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS mempool_transactions (
+                id TEXT PRIMARY KEY,
+                from_address TEXT NOT NULL,
+                nonce INTEGER NOT NULL,
+                data TEXT NOT NULL,
+                saved_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to create mempool_transactions table: {}", e),
+        )))?;
+
         debug!("Database migrations completed successfully");
         Ok(())
     }
 
     pub async fn save_block(&self, block: &Block) -> Result<()> {
+        self.save_block_with_contracts(block, &[], &ContractChanges::default()).await
+    }
+
+    /// Saves `block` together with the receipts of its contract transactions
+    /// and the contract changes they caused, in one database transaction.
+    pub async fn save_block_with_contracts(
+        &self,
+        block: &Block,
+        receipts: &[ContractReceipt],
+        changes: &ContractChanges,
+    ) -> Result<()> {
         debug!("Saving block #{} to database", block.index);
 
         let block_data = serde_json::to_string(block)
@@ -188,6 +386,103 @@ impl BlockchainStorage {
                 std::io::ErrorKind::Other,
                 format!("Failed to insert transaction: {}", e),
             )))?;
+
+            for (output_index, (to, amount)) in transaction.credits().into_iter().enumerate() {
+                sqlx::query(
+                    r#"
+                    INSERT INTO transaction_outputs (transaction_id, output_index, block_index, from_address, to_address, amount)
+                    VALUES (?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(&transaction.id)
+                .bind(output_index as i64)
+                .bind(block.index as i64)
+                .bind(&transaction.from)
+                .bind(to)
+                .bind(amount)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to insert transaction output: {}", e),
+                )))?;
+            }
+        }
+
+        for receipt in receipts {
+            sqlx::query(
+                r#"
+                INSERT INTO contract_receipts (transaction_id, block_index, success, result)
+                VALUES (?, ?, ?, ?)
+                "#,
+            )
+            .bind(&receipt.transaction_id)
+            .bind(block.index as i64)
+            .bind(receipt.result.success)
+            .bind(serde_json::to_string(&receipt.result)?)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to insert receipt of {}: {}", receipt.transaction_id, e),
+            )))?;
+        }
+
+        for contract in &changes.deployed {
+            sqlx::query(
+                r#"
+                INSERT INTO smart_contracts (id, name, owner, code, abi, gas_limit, deployed_at, balance)
+                VALUES (?, ?, ?, ?, ?, ?, ?, 0.0)
+                "#,
+            )
+            .bind(&contract.id)
+            .bind(&contract.name)
+            .bind(&contract.owner)
+            .bind(&contract.code)
+            .bind(serde_json::to_string(&contract.abi)?)
+            .bind(contract.gas_limit as i64)
+            .bind(contract.deployed_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to insert contract {}: {}", contract.id, e),
+            )))?;
+        }
+
+        for (contract_id, writes) in &changes.storage {
+            for (key, value) in writes {
+                let query = match value {
+                    Some(value) => sqlx::query(
+                        r#"
+                        INSERT INTO contract_states (contract_id, key, value, updated_at)
+                        VALUES (?, ?, ?, ?)
+                        ON CONFLICT (contract_id, key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+                        "#,
+                    )
+                    .bind(contract_id)
+                    .bind(key)
+                    .bind(serde_json::to_string(value)?)
+                    .bind(block.timestamp.to_rfc3339()),
+                    None => sqlx::query("DELETE FROM contract_states WHERE contract_id = ? AND key = ?")
+                        .bind(contract_id)
+                        .bind(key),
+                };
+                query
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                        format!("Failed to write state of contract {}: {}", contract_id, e),
+                    )))?;
+            }
+        }
+
+        for (contract_id, balance) in &changes.balances {
+            sqlx::query("UPDATE smart_contracts SET balance = ? WHERE id = ?")
+                .bind(balance)
+                .bind(contract_id)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to update balance of contract {}: {}", contract_id, e),
+                )))?;
         }
 
         tx.commit().await.map_err(|e| {
@@ -259,24 +554,89 @@ impl BlockchainStorage {
         Ok(max_index.map(|i| i as u64))
     }
 
-    pub async fn save_wallet(&self, wallet: &Wallet) -> Result<()> {
+    async fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let rows = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to inspect table {}: {}", table, e),
+            )))?;
+
+        Ok(rows.iter().any(|row| row.get::<String, _>("name") == column))
+    }
+
+    /// Rewrites wallet addresses stored in the old truncated-hex format to the
+    /// checksummed format, recomputing them from the stored public key.
+    async fn migrate_wallet_addresses(&self) -> Result<()> {
+        let rows = sqlx::query("SELECT address, public_key FROM wallets")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load wallets: {}", e),
+            )))?;
+
+        for row in rows {
+            let address: String = row.get("address");
+            let public_key: String = row.get("public_key");
+            let public_key = hex::decode(&public_key)
+                .map_err(|e| BlockchainError::Wallet {
+                    message: format!("Invalid public key for wallet {}: {}", address, e),
+                })
+                .and_then(|bytes| PublicKey::from_bytes(&bytes))?;
+
+            let current = public_key.to_address();
+            if current == address {
+                continue;
+            }
+
+            sqlx::query("UPDATE wallets SET address = ? WHERE address = ?")
+                .bind(&current)
+                .bind(&address)
+                .execute(&self.pool)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to migrate wallet address {}: {}", address, e),
+                )))?;
+            info!("Wallet address {} migrated to {}", address, current);
+        }
+
+        Ok(())
+    }
+
+    /// Whether the wallets table still holds private keys in clear text.
+    pub async fn has_plaintext_wallets(&self) -> Result<bool> {
+        self.has_column("wallets", "private_key").await
+    }
+
+    pub async fn save_wallet(&self, wallet: &Wallet, password: &str) -> Result<()> {
         debug!("Saving wallet '{}' to database", wallet.name);
 
+        if self.has_plaintext_wallets().await? {
+            return Err(BlockchainError::Wallet {
+                message: "Wallet table contains plaintext keys, run `blockchain migrate-wallets` first".to_string(),
+            });
+        }
+
+        let keypair = wallet.keypair().ok_or_else(|| BlockchainError::Wallet {
+            message: format!("Wallet '{}' uses a remote signer and has no key to store", wallet.name),
+        })?;
+        let private_key = Zeroizing::new(keypair.to_private_key_bytes());
+        let encrypted = EncryptedKey::encrypt(private_key.as_ref(), password)?;
+
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO wallets (address, name, public_key, private_key, created_at)
+            INSERT OR REPLACE INTO wallets (address, name, public_key, private_key_encrypted, created_at)
             VALUES (?, ?, ?, ?, ?)
             "#,
         )
-        .bind(&wallet.address())
+        .bind(wallet.address())
         .bind(&wallet.name)
-        .bind(&wallet.keypair.public_key().to_string())
-        .bind(hex::encode(wallet.keypair.to_private_key_bytes()))
+        .bind(wallet.public_key().to_string())
+        .bind(encrypted.to_json()?)
         .bind(Utc::now().to_rfc3339())
         .execute(&self.pool)
         .await
-        .map_err(|e| BlockchainError::Io(std::io::Error::new(
-            std::io::ErrorKind::Other,
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
             format!("Failed to save wallet: {}", e),
         )))?;
 
@@ -284,37 +644,314 @@ impl BlockchainStorage {
         Ok(())
     }
 
-    pub async fn load_wallet(&self, address: &str) -> Result<Option<Wallet>> {
+    /// Decrypts and returns the wallet stored under `address`.
+    pub async fn load_wallet(&self, address: &str, password: &str) -> Result<Option<Wallet>> {
         debug!("Loading wallet with address '{}' from database", address);
 
-        let row = sqlx::query("SELECT name, private_key FROM wallets WHERE address = ?")
+        let info = match self.get_wallet_info(address).await? {
+            Some(info) => info,
+            None => return Ok(None),
+        };
+
+        let encrypted = match self.load_encrypted_key(address).await? {
+            Some(encrypted) => encrypted,
+            None => return Ok(None),
+        };
+
+        let private_key = encrypted.decrypt(password)?;
+        let wallet = Wallet::from_private_key(info.name, &private_key)?;
+        Ok(Some(wallet))
+    }
+
+    /// Returns the stored keystore blob for `address` without decrypting it.
+    pub async fn load_encrypted_key(&self, address: &str) -> Result<Option<EncryptedKey>> {
+        let row = sqlx::query("SELECT private_key_encrypted FROM wallets WHERE address = ?")
             .bind(address)
             .fetch_optional(&self.pool)
             .await
-            .map_err(|e| BlockchainError::Io(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
                 format!("Failed to load wallet: {}", e),
             )))?;
 
-        if let Some(row) = row {
-            let name: String = row.get("name");
-            let private_key_hex: String = row.get("private_key");
-            let private_key = hex::decode(private_key_hex)
-                .map_err(|e| BlockchainError::InvalidTransaction {
-                    message: format!("Invalid private key format: {}", e),
+        match row {
+            Some(row) => {
+                let encrypted: Option<String> = row.get("private_key_encrypted");
+                let encrypted = encrypted.ok_or_else(|| BlockchainError::Wallet {
+                    message: "Wallet key is stored unencrypted, run `blockchain migrate-wallets` first".to_string(),
                 })?;
+                Ok(Some(EncryptedKey::from_json(&encrypted)?))
+            }
+            None => Ok(None),
+        }
+    }
 
-            let wallet = Wallet::from_private_key(name, &private_key)?;
-            Ok(Some(wallet))
-        } else {
-            Ok(None)
+    pub async fn get_wallet_info(&self, address: &str) -> Result<Option<WalletInfo>> {
+        let row = sqlx::query("SELECT address, name, public_key, created_at FROM wallets WHERE address = ?")
+            .bind(address)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load wallet: {}", e),
+            )))?;
+
+        row.map(|row| wallet_info_from_row(&row)).transpose()
+    }
+
+    /// Stores an HD wallet's seed encrypted under `password`. Accounts derived
+    /// from it are saved separately through `save_wallet`.
+    pub async fn save_hd_wallet(&self, hd_wallet: &HdWallet, password: &str) -> Result<()> {
+        debug!("Saving HD wallet '{}' to database", hd_wallet.name);
+
+        let existing = sqlx::query("SELECT name FROM hd_wallets WHERE name = ?")
+            .bind(&hd_wallet.name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load HD wallet: {}", e),
+            )))?;
+        if existing.is_some() {
+            return Err(BlockchainError::Wallet {
+                message: format!("HD wallet '{}' already exists", hd_wallet.name),
+            });
         }
+
+        let encrypted = EncryptedKey::encrypt(hd_wallet.seed(), password)?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO hd_wallets (name, seed_encrypted, next_index, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(&hd_wallet.name)
+        .bind(encrypted.to_json()?)
+        .bind(hd_wallet.next_index as i64)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to save HD wallet: {}", e),
+        )))?;
+
+        info!("HD wallet '{}' saved to database", hd_wallet.name);
+        Ok(())
+    }
+
+    /// Decrypts and returns the HD wallet stored under `name`.
+    pub async fn load_hd_wallet(&self, name: &str, password: &str) -> Result<Option<HdWallet>> {
+        let row = sqlx::query("SELECT seed_encrypted, next_index FROM hd_wallets WHERE name = ?")
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load HD wallet: {}", e),
+            )))?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let encrypted = EncryptedKey::from_json(&row.get::<String, _>("seed_encrypted"))?;
+        let seed = encrypted.decrypt(password)?;
+        let next_index: i64 = row.get("next_index");
+
+        Ok(Some(HdWallet::from_seed(name.to_string(), &seed, next_index as u32)))
+    }
+
+    /// Derives the next account of HD wallet `name`, stores it as a regular
+    /// encrypted wallet and advances the wallet's derivation index. The index
+    /// is claimed and advanced in one statement, so concurrent derivations
+    /// never get the same account.
+    pub async fn derive_hd_account(&self, name: &str, password: &str) -> Result<Option<(u32, Wallet)>> {
+        let hd_wallet = match self.load_hd_wallet(name, password).await? {
+            Some(hd_wallet) => hd_wallet,
+            None => return Ok(None),
+        };
+
+        let row = sqlx::query(
+            r#"
+            UPDATE hd_wallets SET next_index = next_index + 1
+            WHERE name = ?
+            RETURNING next_index - 1 AS claimed_index
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to update HD wallet: {}", e),
+        )))?;
+        let index = match row {
+            Some(row) => row.get::<i64, _>("claimed_index") as u32,
+            None => return Ok(None),
+        };
+
+        let wallet = hd_wallet.derive_account(index)?;
+        self.save_wallet(&wallet, password).await?;
+
+        Ok(Some((index, wallet)))
+    }
+
+    pub async fn save_multisig_account(&self, account: &MultisigAccount) -> Result<()> {
+        let public_keys: Vec<String> = account.public_keys.iter().map(|key| key.to_string()).collect();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO multisig_accounts (address, threshold, public_keys, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(account.address())
+        .bind(account.threshold as i64)
+        .bind(serde_json::to_string(&public_keys)?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to save multisig account: {}", e),
+        )))?;
+
+        info!("Multisig account {} saved to database", account.address());
+        Ok(())
+    }
+
+    pub async fn load_multisig_account(&self, address: &str) -> Result<Option<MultisigAccount>> {
+        let row = sqlx::query("SELECT threshold, public_keys FROM multisig_accounts WHERE address = ?")
+            .bind(address)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load multisig account: {}", e),
+            )))?;
+
+        let row = match row {
+            Some(row) => row,
+            None => return Ok(None),
+        };
+
+        let threshold: i64 = row.get("threshold");
+        let public_keys: Vec<String> = serde_json::from_str(&row.get::<String, _>("public_keys"))?;
+        let public_keys = public_keys
+            .iter()
+            .map(|key| PublicKey::from_hex(key))
+            .collect::<Result<Vec<_>>>()?;
+
+        MultisigAccount::new(threshold as usize, public_keys).map(Some)
+    }
+
+    /// Stores a multisig transaction that is still collecting signatures.
+    pub async fn save_multisig_proposal(&self, transaction: &Transaction) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO multisig_proposals (id, address, data, created_at)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(&transaction.id)
+        .bind(&transaction.from)
+        .bind(transaction.serialize()?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to save multisig proposal: {}", e),
+        )))?;
+
+        Ok(())
+    }
+
+    pub async fn load_multisig_proposal(&self, id: &str) -> Result<Option<Transaction>> {
+        let row = sqlx::query("SELECT data FROM multisig_proposals WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load multisig proposal: {}", e),
+            )))?;
+
+        row.map(|row| serde_json::from_str(&row.get::<String, _>("data")).map_err(BlockchainError::from))
+            .transpose()
+    }
+
+    pub async fn delete_multisig_proposal(&self, id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM multisig_proposals WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to delete multisig proposal: {}", e),
+            )))?;
+
+        Ok(())
+    }
+
+    /// Encrypts every plaintext private key with `password` and drops the
+    /// plaintext column. Returns the number of wallets re-encrypted.
+    pub async fn migrate_plaintext_wallets(&self, password: &str) -> Result<usize> {
+        if !self.has_plaintext_wallets().await? {
+            return Ok(0);
+        }
+
+        info!("Encrypting plaintext wallet keys");
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            BlockchainError::Io(std::io::Error::other(
+                format!("Failed to begin transaction: {}", e),
+            ))
+        })?;
+
+        let rows = sqlx::query(
+            "SELECT address, private_key FROM wallets WHERE private_key_encrypted IS NULL",
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to load plaintext wallets: {}", e),
+        )))?;
+
+        for row in &rows {
+            let address: String = row.get("address");
+            let private_key_hex: Zeroizing<String> = Zeroizing::new(row.get("private_key"));
+            let private_key = Zeroizing::new(hex::decode(private_key_hex.as_str()).map_err(|e| {
+                BlockchainError::Wallet {
+                    message: format!("Invalid private key format for {}: {}", address, e),
+                }
+            })?);
+
+            let encrypted = EncryptedKey::encrypt(&private_key, password)?;
+
+            sqlx::query("UPDATE wallets SET private_key_encrypted = ? WHERE address = ?")
+                .bind(encrypted.to_json()?)
+                .bind(&address)
+                .execute(&mut *tx)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to encrypt wallet {}: {}", address, e),
+                )))?;
+        }
+
+        sqlx::query("ALTER TABLE wallets DROP COLUMN private_key")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to drop plaintext key column: {}", e),
+            )))?;
+
+        tx.commit().await.map_err(|e| {
+            BlockchainError::Io(std::io::Error::other(
+                format!("Failed to commit transaction: {}", e),
+            ))
+        })?;
+
+        info!("Encrypted {} plaintext wallet keys", rows.len());
+        Ok(rows.len())
     }
 
     pub async fn list_wallets(&self) -> Result<Vec<WalletInfo>> {
         debug!("Loading all wallets from database");
 
-        let rows = sqlx::query("SELECT address, name, created_at FROM wallets ORDER BY created_at")
+        let rows = sqlx::query("SELECT address, name, public_key, created_at FROM wallets ORDER BY created_at")
             .fetch_all(&self.pool)
             .await
             .map_err(|e| BlockchainError::Io(std::io::Error::new(
@@ -322,25 +959,7 @@ impl BlockchainStorage {
                 format!("Failed to load wallets: {}", e),
             )))?;
 
-        let mut wallets = Vec::new();
-        for row in rows {
-            let address: String = row.get("address");
-            let name: String = row.get("name");
-            let created_at_str: String = row.get("created_at");
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map_err(|e| BlockchainError::InvalidTransaction {
-                    message: format!("Invalid timestamp format: {}", e),
-                })?
-                .with_timezone(&Utc);
-
-            wallets.push(WalletInfo {
-                address,
-                name,
-                created_at,
-            });
-        }
-
-        Ok(wallets)
+        rows.iter().map(wallet_info_from_row).collect()
     }
 
     pub async fn save_mining_stats(&self, stats: &MiningStats) -> Result<()> {
@@ -397,6 +1016,185 @@ impl BlockchainStorage {
         }
     }
 
+    /// Replaces the persisted mempool with `transactions`.
+    pub async fn save_mempool(&self, transactions: &[Transaction]) -> Result<()> {
+        debug!("Saving {} pending transactions to database", transactions.len());
+
+        let mut tx = self.pool.begin().await.map_err(|e| {
+            BlockchainError::Io(std::io::Error::other(
+                format!("Failed to begin transaction: {}", e),
+            ))
+        })?;
+
+        sqlx::query("DELETE FROM mempool_transactions")
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to clear mempool: {}", e),
+            )))?;
+
+        let saved_at = Utc::now().to_rfc3339();
+        for transaction in transactions {
+            let data = serde_json::to_string(transaction)?;
+
+            sqlx::query(
+                r#"
+                INSERT INTO mempool_transactions (id, from_address, nonce, data, saved_at)
+                VALUES (?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(&transaction.id)
+            .bind(&transaction.from)
+            .bind(transaction.nonce as i64)
+            .bind(&data)
+            .bind(&saved_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to save pending transaction: {}", e),
+            )))?;
+        }
+
+        tx.commit().await.map_err(|e| {
+            BlockchainError::Io(std::io::Error::other(
+                format!("Failed to commit transaction: {}", e),
+            ))
+        })?;
+
+        Ok(())
+    }
+
+    pub async fn load_mempool(&self) -> Result<Vec<Transaction>> {
+        debug!("Loading pending transactions from database");
+
+        let rows = sqlx::query("SELECT data FROM mempool_transactions ORDER BY from_address, nonce")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load mempool: {}", e),
+            )))?;
+
+        let mut transactions = Vec::new();
+        for row in rows {
+            let data: String = row.get("data");
+            transactions.push(serde_json::from_str(&data)?);
+        }
+
+        Ok(transactions)
+    }
+
+    /// Deployed contracts with their storage and balance, for restoring the
+    /// contract engine on startup.
+    pub async fn load_contracts(&self) -> Result<Vec<(SmartContract, ContractState)>> {
+        let rows = sqlx::query(
+            "SELECT id, name, owner, code, abi, gas_limit, deployed_at, created_at, balance FROM smart_contracts WHERE is_active",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to load contracts: {}", e),
+        )))?;
+
+        let mut contracts = Vec::with_capacity(rows.len());
+        for row in rows {
+            let id: String = row.get("id");
+            let abi: Option<String> = row.get("abi");
+            let deployed_at: String = row
+                .get::<Option<String>, _>("deployed_at")
+                .unwrap_or_else(|| row.get("created_at"));
+
+            let contract = SmartContract {
+                id: id.clone(),
+                name: row.get("name"),
+                code: row.get("code"),
+                abi: abi.map(|abi| serde_json::from_str(&abi)).transpose()?.unwrap_or_default(),
+                owner: row.get("owner"),
+                deployed_at: DateTime::parse_from_rfc3339(&deployed_at)
+                    .map(|time| time.with_timezone(&Utc))
+                    .unwrap_or_else(|_| Utc::now()),
+                gas_limit: row.get::<i64, _>("gas_limit") as u64,
+            };
+
+            let entries = sqlx::query("SELECT key, value FROM contract_states WHERE contract_id = ?")
+                .bind(&id)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                    format!("Failed to load state of contract {}: {}", id, e),
+                )))?;
+            let mut storage = HashMap::with_capacity(entries.len());
+            for entry in entries {
+                let value: String = entry.get("value");
+                storage.insert(entry.get("key"), serde_json::from_str::<ContractValue>(&value)?);
+            }
+
+            let state = ContractState {
+                storage,
+                balance: row.get("balance"),
+            };
+            contracts.push((contract, state));
+        }
+
+        Ok(contracts)
+    }
+
+    /// Receipts of all confirmed contract transactions, in block order.
+    pub async fn load_contract_receipts(&self) -> Result<Vec<ContractReceipt>> {
+        let rows = sqlx::query("SELECT transaction_id, result FROM contract_receipts ORDER BY block_index")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| BlockchainError::Io(std::io::Error::other(
+                format!("Failed to load contract receipts: {}", e),
+            )))?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(ContractReceipt {
+                    transaction_id: row.get("transaction_id"),
+                    result: serde_json::from_str(&row.get::<String, _>("result"))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Confirmed transactions that `address` sent or received, oldest first.
+    /// Batch transfers are found through any of their outputs.
+    pub async fn load_address_transactions(&self, address: &str) -> Result<Vec<Transaction>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT block_index, transaction_id FROM transaction_outputs
+            WHERE to_address = ? OR from_address = ?
+            ORDER BY block_index
+            "#,
+        )
+        .bind(address)
+        .bind(address)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| BlockchainError::Io(std::io::Error::other(
+            format!("Failed to load address transactions: {}", e),
+        )))?;
+
+        let mut transactions = Vec::new();
+        let mut current: Option<Block> = None;
+        for row in rows {
+            let block_index = row.get::<i64, _>("block_index") as u64;
+            let transaction_id: String = row.get("transaction_id");
+
+            if current.as_ref().is_none_or(|block| block.index != block_index) {
+                current = self.load_block(block_index).await?;
+            }
+            if let Some(transaction) = current
+                .as_ref()
+                .and_then(|block| block.transactions.iter().find(|tx| tx.id == transaction_id))
+            {
+                transactions.push(transaction.clone());
+            }
+        }
+
+        Ok(transactions)
+    }
+
     pub async fn get_transaction_count(&self) -> Result<u64> {
         let row = sqlx::query("SELECT COUNT(*) as count FROM transactions")
             .fetch_one(&self.pool)
@@ -416,9 +1214,26 @@ impl BlockchainStorage {
     }
 }
 
+fn wallet_info_from_row(row: &SqliteRow) -> Result<WalletInfo> {
+    let created_at_str: String = row.get("created_at");
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map_err(|e| BlockchainError::InvalidTransaction {
+            message: format!("Invalid timestamp format: {}", e),
+        })?
+        .with_timezone(&Utc);
+
+    Ok(WalletInfo {
+        address: row.get("address"),
+        name: row.get("name"),
+        public_key: row.get("public_key"),
+        created_at,
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct WalletInfo {
     pub address: String,
     pub name: String,
+    pub public_key: String,
     pub created_at: DateTime<Utc>,
 }
\ No newline at end of file
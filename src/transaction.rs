@@ -1,19 +1,94 @@
 use serde::{Deserialize, Serialize};
-use crate::crypto::{DigitalSignature, PublicKey};
+use crate::address::{contract_address, is_multisig_address};
+use crate::contracts::{ContractABI, ContractValue};
+use crate::crypto::{DigitalSignature, PublicKey, Wallet};
+use crate::multisig::{MultisigAuthorization, PartialSignature};
 use crate::errors::{BlockchainError, Result};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
 
+/// Upper bound on the outputs of a batch transfer.
+pub const MAX_BATCH_OUTPUTS: usize = 1000;
+
+/// One recipient of a batch transfer.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TransferOutput {
+    pub to: String,
+    pub amount: f64,
+}
+
+/// What a transaction does. Part of the signed payload, so it cannot be
+/// changed without invalidating the signature.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TransactionKind {
+    #[default]
+    Transfer,
+    /// Block reward paid by the protocol to the miner.
+    Coinbase,
+    /// Pays every output from the sender at once; `amount` is their total.
+    BatchTransfer { outputs: Vec<TransferOutput> },
+    /// Moves the sender's signing authority to `new_public_key`.
+    KeyRotation { new_public_key: PublicKey },
+    /// Deploys a contract whose id is the transaction's `to`.
+    ContractDeploy {
+        name: String,
+        #[serde(with = "base64_bytes")]
+        code: Vec<u8>,
+        /// Empty to use the ABI embedded in the module's `abi` custom section.
+        #[serde(default)]
+        abi: ContractABI,
+        gas_limit: u64,
+    },
+    /// Calls `function` on the contract at `to`, sending `amount` as value.
+    ContractCall {
+        function: String,
+        args: Vec<ContractValue>,
+        gas_limit: u64,
+    },
+}
+
+impl TransactionKind {
+    pub fn is_transfer(&self) -> bool {
+        matches!(self, TransactionKind::Transfer)
+    }
+
+    /// Whether `to` is a contract id rather than an account address.
+    pub fn targets_contract(&self) -> bool {
+        matches!(self, TransactionKind::ContractDeploy { .. } | TransactionKind::ContractCall { .. })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Transaction {
     pub id: String,
     pub from: String,
     pub to: String,
     pub amount: f64,
+    #[serde(default)]
+    pub kind: TransactionKind,
+    #[serde(default)]
+    pub fee: f64,
+    #[serde(default)]
+    pub nonce: u64,
     pub data: Option<String>,
     pub timestamp: DateTime<Utc>,
     pub signature: Option<DigitalSignature>,
     pub from_public_key: Option<PublicKey>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub multisig: Option<MultisigAuthorization>,
+    /// Only valid in blocks above this height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_after_height: Option<u64>,
+    /// Only valid in blocks timestamped after this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_after_time: Option<DateTime<Utc>>,
+    /// Only valid in blocks below this height.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_height: Option<u64>,
+    /// Only valid in blocks timestamped before this time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at_time: Option<DateTime<Utc>>,
 }
 
 impl Transaction {
@@ -41,10 +116,18 @@ impl Transaction {
             from,
             to,
             amount,
+            kind: TransactionKind::Transfer,
+            fee: 0.0,
+            nonce: 0,
             data,
             timestamp: Utc::now(),
             signature: None,
             from_public_key: None,
+            multisig: None,
+            valid_after_height: None,
+            valid_after_time: None,
+            expires_at_height: None,
+            expires_at_time: None,
         })
     }
 
@@ -68,42 +151,329 @@ impl Transaction {
             from: "genesis".to_string(),
             to: "genesis".to_string(),
             amount: 0.0,
+            kind: TransactionKind::Transfer,
+            fee: 0.0,
+            nonce: 0,
             data: Some("Genesis transaction".to_string()),
             timestamp: Utc::now(),
             signature: None,
             from_public_key: None,
+            multisig: None,
+            valid_after_height: None,
+            valid_after_time: None,
+            expires_at_height: None,
+            expires_at_time: None,
         }
     }
 
+    /// Unsigned transfer to several recipients, funded all-or-nothing.
+    pub fn batch_transfer(from: String, outputs: Vec<TransferOutput>, data: Option<String>) -> Result<Self> {
+        let total = outputs.iter().map(|output| output.amount).sum();
+        let mut transaction = Self::new(from.clone(), from, total, data)?;
+        transaction.kind = TransactionKind::BatchTransfer { outputs };
+        transaction.check_kind()?;
+        Ok(transaction)
+    }
+
+    /// Block reward of `amount` paid to `miner_address`.
+    pub fn coinbase(miner_address: String, amount: f64) -> Result<Self> {
+        let mut transaction = Self::new("miner".to_string(), miner_address, amount, None)?;
+        transaction.kind = TransactionKind::Coinbase;
+        Ok(transaction)
+    }
+
+    /// Unsigned zero-value self-transfer reusing `original`'s nonce; once signed
+    /// with a higher fee it replaces `original` in the mempool.
+    pub fn cancellation(original: &Transaction, fee: f64) -> Result<Self> {
+        let mut transaction = Self::new(
+            original.from.clone(),
+            original.from.clone(),
+            0.0,
+            Some(format!("Cancel {}", original.id)),
+        )?;
+        transaction.fee = fee;
+        transaction.nonce = original.nonce;
+        Ok(transaction)
+    }
+
+    /// Unsigned transaction moving `address`'s signing authority to
+    /// `new_public_key`. It must be signed by the currently authorized key.
+    pub fn key_rotation(address: String, new_public_key: PublicKey) -> Result<Self> {
+        if is_multisig_address(&address) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: "Multisig accounts cannot rotate to a single key".to_string(),
+            });
+        }
+
+        let mut transaction = Self::new(address.clone(), address, 0.0, None)?;
+        transaction.kind = TransactionKind::KeyRotation { new_public_key };
+        Ok(transaction)
+    }
+
+    /// Unsigned deployment of `code`. The new contract's id, which is also
+    /// the transaction's `to`, is derived from `owner` and the nonce; use
+    /// `set_nonce` to change it.
+    pub fn contract_deploy(
+        owner: String,
+        name: String,
+        code: Vec<u8>,
+        abi: ContractABI,
+        gas_limit: u64,
+    ) -> Result<Self> {
+        let mut transaction = Self::new(owner.clone(), contract_address(&owner, 0), 0.0, None)?;
+        transaction.kind = TransactionKind::ContractDeploy {
+            name,
+            code,
+            abi,
+            gas_limit,
+        };
+        Ok(transaction)
+    }
+
+    /// Sets the nonce, re-deriving the contract id of a deployment.
+    pub fn set_nonce(&mut self, nonce: u64) {
+        self.nonce = nonce;
+        if matches!(self.kind, TransactionKind::ContractDeploy { .. }) {
+            self.to = contract_address(&self.from, nonce);
+        }
+    }
+
+    /// Unsigned call of `function` on `contract_id`, sending `value` to the contract.
+    pub fn contract_call(
+        caller: String,
+        contract_id: String,
+        function: String,
+        args: Vec<ContractValue>,
+        value: f64,
+        gas_limit: u64,
+    ) -> Result<Self> {
+        let mut transaction = Self::new(caller, contract_id, value, None)?;
+        transaction.kind = TransactionKind::ContractCall {
+            function,
+            args,
+            gas_limit,
+        };
+        Ok(transaction)
+    }
+
     pub fn signable_data(&self) -> Result<Vec<u8>> {
         let signable = SignableTransaction {
             id: &self.id,
             from: &self.from,
             to: &self.to,
             amount: self.amount,
+            kind: &self.kind,
+            fee: self.fee,
+            nonce: self.nonce,
             data: self.data.as_ref(),
             timestamp: self.timestamp,
+            valid_after_height: self.valid_after_height,
+            valid_after_time: self.valid_after_time,
+            expires_at_height: self.expires_at_height,
+            expires_at_time: self.expires_at_time,
         };
 
         serde_json::to_vec(&signable).map_err(BlockchainError::from)
     }
 
+    /// Signs with `wallet`, attaching its public key so nodes can verify the
+    /// signature without further lookups.
+    pub fn sign(&mut self, wallet: &Wallet) -> Result<()> {
+        let signable_data = self.signable_data()?;
+        self.signature = Some(wallet.sign_transaction(&signable_data)?);
+        self.from_public_key = Some(wallet.public_key().clone());
+        Ok(())
+    }
+
     pub fn verify_signature(&self) -> bool {
+        if let Some(multisig) = &self.multisig {
+            return self
+                .signable_data()
+                .is_ok_and(|data| multisig.verify(&self.from, &data));
+        }
+
+        if self.is_multisig() {
+            return false;
+        }
+
         if let (Some(signature), Some(public_key)) = (&self.signature, &self.from_public_key) {
             if let Ok(signable_data) = self.signable_data() {
                 return public_key.verify(&signable_data, signature);
             }
         }
 
-        self.from == "genesis" || self.from == "miner"
+        self.is_system()
     }
 
     pub fn serialize(&self) -> Result<String> {
         serde_json::to_string(self).map_err(BlockchainError::from)
     }
 
+    /// Serialized size in bytes, used to rank pending transactions by fee rate.
+    pub fn size(&self) -> usize {
+        self.serialize().map(|s| s.len()).unwrap_or(0)
+    }
+
+    pub fn fee_rate(&self) -> f64 {
+        match self.size() {
+            0 => 0.0,
+            size => self.fee / size as f64,
+        }
+    }
+
+    /// Whether this spends from a multisig address, which requires an M-of-N
+    /// authorization instead of a single signature.
+    pub fn is_multisig(&self) -> bool {
+        self.multisig.is_some() || is_multisig_address(&self.from)
+    }
+
+    /// Verifies and records a co-signer's signature on a multisig transaction.
+    pub fn add_multisig_signature(&mut self, partial: PartialSignature) -> Result<()> {
+        let message = self.signable_data()?;
+        match &mut self.multisig {
+            Some(multisig) => multisig.add_signature(&message, partial),
+            None => Err(BlockchainError::InvalidTransaction {
+                message: "Transaction is not a multisig transaction".to_string(),
+            }),
+        }
+    }
+
+    /// The key this transaction rotates its sender to, if it is a key rotation.
+    pub fn new_public_key(&self) -> Option<&PublicKey> {
+        match &self.kind {
+            TransactionKind::KeyRotation { new_public_key } => Some(new_public_key),
+            _ => None,
+        }
+    }
+
+    /// Checks the fields each kind depends on: key rotations are signed
+    /// zero-value self-transfers from a single-key account, only the protocol
+    /// pays coinbases, and a deployment's `to` is its own id.
+    pub fn check_kind(&self) -> Result<()> {
+        let valid = match &self.kind {
+            TransactionKind::Transfer | TransactionKind::ContractCall { .. } => true,
+            TransactionKind::Coinbase => self.from == "miner",
+            TransactionKind::BatchTransfer { outputs } => {
+                let total: f64 = outputs.iter().map(|output| output.amount).sum();
+                !outputs.is_empty()
+                    && outputs.len() <= MAX_BATCH_OUTPUTS
+                    && outputs
+                        .iter()
+                        .all(|output| !output.to.trim().is_empty() && output.amount.is_finite() && output.amount > 0.0)
+                    && self.to == self.from
+                    && (total - self.amount).abs() < 1e-9
+            }
+            TransactionKind::KeyRotation { .. } => {
+                !self.is_multisig() && self.signature.is_some() && self.to == self.from && self.amount == 0.0
+            }
+            TransactionKind::ContractDeploy { name, code, .. } => {
+                self.to == contract_address(&self.from, self.nonce) && !name.trim().is_empty() && !code.is_empty()
+            }
+        };
+
+        if !valid {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} is not a valid {} transaction", self.id, self.kind_name()),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn kind_name(&self) -> &'static str {
+        match self.kind {
+            TransactionKind::Transfer => "transfer",
+            TransactionKind::Coinbase => "coinbase",
+            TransactionKind::BatchTransfer { .. } => "batch_transfer",
+            TransactionKind::KeyRotation { .. } => "key_rotation",
+            TransactionKind::ContractDeploy { .. } => "contract_deploy",
+            TransactionKind::ContractCall { .. } => "contract_call",
+        }
+    }
+
+    /// Whether the lock window is non-empty, i.e. some block could include this.
+    pub fn check_lock_window(&self) -> Result<()> {
+        let height_window_empty = matches!(
+            (self.valid_after_height, self.expires_at_height),
+            (Some(after), Some(expires)) if expires <= after + 1
+        );
+        let time_window_empty = matches!(
+            (self.valid_after_time, self.expires_at_time),
+            (Some(after), Some(expires)) if expires <= after
+        );
+
+        if height_window_empty || time_window_empty {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} expires before it becomes valid", self.id),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Whether the time and height locks have passed for a block at `height`
+    /// timestamped `time`.
+    pub fn is_unlocked(&self, height: u64, time: DateTime<Utc>) -> bool {
+        self.valid_after_height.is_none_or(|after| height > after)
+            && self.valid_after_time.is_none_or(|after| time > after)
+    }
+
+    /// Whether a block at `height` timestamped `time` is too late to include this.
+    pub fn is_expired(&self, height: u64, time: DateTime<Utc>) -> bool {
+        self.expires_at_height.is_some_and(|expires| height >= expires)
+            || self.expires_at_time.is_some_and(|expires| time >= expires)
+    }
+
+    /// Checks this may be included in a block at `height` timestamped `time`.
+    pub fn check_locks(&self, height: u64, time: DateTime<Utc>) -> Result<()> {
+        if !self.is_unlocked(height, time) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} is locked until a later block", self.id),
+            });
+        }
+
+        if self.is_expired(height, time) {
+            return Err(BlockchainError::InvalidTransaction {
+                message: format!("Transaction {} has expired", self.id),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Recipients and the amount each is credited: a batch's outputs, or `to`
+    /// and `amount` for every other kind.
+    pub fn credits(&self) -> Vec<(&str, f64)> {
+        match &self.kind {
+            TransactionKind::BatchTransfer { outputs } => outputs
+                .iter()
+                .map(|output| (output.to.as_str(), output.amount))
+                .collect(),
+            _ => vec![(self.to.as_str(), self.amount)],
+        }
+    }
+
+    /// Total credited to `address` by this transaction.
+    pub fn amount_to(&self, address: &str) -> f64 {
+        self.credits()
+            .into_iter()
+            .filter(|(to, _)| *to == address)
+            .map(|(_, amount)| amount)
+            .sum()
+    }
+
+    pub fn is_batch(&self) -> bool {
+        matches!(self.kind, TransactionKind::BatchTransfer { .. })
+    }
+
     pub fn is_coinbase(&self) -> bool {
-        self.from == "miner"
+        self.from == "miner" || self.kind == TransactionKind::Coinbase
+    }
+
+    /// Genesis and coinbase transactions, which are created by the protocol
+    /// rather than signed by a sender.
+    pub fn is_system(&self) -> bool {
+        self.from == "genesis" || self.is_coinbase()
     }
 }
 
@@ -113,6 +483,37 @@ struct SignableTransaction<'a> {
     from: &'a str,
     to: &'a str,
     amount: f64,
+    // Omitted for transfers so signatures made before kinds existed still verify
+    #[serde(skip_serializing_if = "is_transfer")]
+    kind: &'a TransactionKind,
+    fee: f64,
+    nonce: u64,
     data: Option<&'a String>,
     timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid_after_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    valid_after_time: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at_height: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires_at_time: Option<DateTime<Utc>>,
+}
+
+fn is_transfer(kind: &&TransactionKind) -> bool {
+    kind.is_transfer()
+}
+
+mod base64_bytes {
+    use base64::prelude::*;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&BASE64_STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        BASE64_STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+    }
 }
\ No newline at end of file